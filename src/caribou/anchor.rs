@@ -0,0 +1,170 @@
+use std::future::Future;
+use std::pin::Pin;
+use crate::caribou::gadget::Gadget;
+use crate::caribou::layout::{Layout, layout_arrange_on, layout_place, layout_unwatch_child,
+                             layout_watch_child};
+use crate::caribou::math::{Region, Scalar, ScalarPair};
+use crate::caribou::measure::Constraints;
+use crate::caribou::state::Arbitrary;
+
+/// Container placing its children relative to its own edges inside its padding, reflowing them
+/// whenever its `dim` changes. Children without [`Anchors`] attached keep their `pos` and `dim`,
+/// as in [`Layout`].
+pub struct AnchorLayout;
+
+/// Constraint of a child along one axis of its parent.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct AxisAnchor {
+    /// Distance from the start edge (left or top).
+    pub start: Option<Scalar>,
+    /// Distance from the end edge (right or bottom).
+    pub end: Option<Scalar>,
+    /// Offset of the child's center from the parent's center.
+    pub center: Option<Scalar>,
+    /// Size as a fraction of the parent's, e.g. `0.5` for half of it.
+    pub fraction: Option<Scalar>,
+}
+
+impl AxisAnchor {
+    /// Position and size within `available` given the measured size. Both edges anchored
+    /// stretch the child between them; otherwise the size is the fraction or the measured one.
+    pub fn resolve(&self, preferred: Scalar, available: Scalar) -> (Scalar, Scalar) {
+        let size = match self.fraction {
+            Some(fraction) => available * fraction,
+            None => preferred,
+        };
+        match (self.start, self.end, self.center) {
+            (Some(start), Some(end), _) => (start, (available - start - end).max(0.0)),
+            (Some(start), None, _) => (start, size),
+            (None, Some(end), _) => (available - end - size, size),
+            (None, None, Some(center)) => ((available - size) / 2.0 + center, size),
+            (None, None, None) => (0.0, size),
+        }
+    }
+}
+
+/// Attached anchors of a child.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Anchors {
+    pub horizontal: AxisAnchor,
+    pub vertical: AxisAnchor,
+}
+
+impl Anchors {
+    pub fn left(mut self, offset: Scalar) -> Self {
+        self.horizontal.start = Some(offset);
+        self
+    }
+
+    pub fn top(mut self, offset: Scalar) -> Self {
+        self.vertical.start = Some(offset);
+        self
+    }
+
+    pub fn right(mut self, offset: Scalar) -> Self {
+        self.horizontal.end = Some(offset);
+        self
+    }
+
+    pub fn bottom(mut self, offset: Scalar) -> Self {
+        self.vertical.end = Some(offset);
+        self
+    }
+
+    /// Anchors all four edges at the same distance.
+    pub fn fill(self, offset: Scalar) -> Self {
+        self.left(offset).top(offset).right(offset).bottom(offset)
+    }
+
+    pub fn center_horizontal(mut self, offset: Scalar) -> Self {
+        self.horizontal.center = Some(offset);
+        self
+    }
+
+    pub fn center_vertical(mut self, offset: Scalar) -> Self {
+        self.vertical.center = Some(offset);
+        self
+    }
+
+    pub fn center(self) -> Self {
+        self.center_horizontal(0.0).center_vertical(0.0)
+    }
+
+    pub fn width_fraction(mut self, fraction: Scalar) -> Self {
+        self.horizontal.fraction = Some(fraction);
+        self
+    }
+
+    pub fn height_fraction(mut self, fraction: Scalar) -> Self {
+        self.vertical.fraction = Some(fraction);
+        self
+    }
+}
+
+const ANCHOR_KEY: &str = "anchor.anchors";
+
+impl AnchorLayout {
+    pub async fn create() -> Gadget {
+        let gadget = Layout::create().await;
+
+        // Fill common properties
+        gadget.type_name.set("AnchorLayout").await;
+
+        // Listen re-layout
+        gadget.children.listen_add(
+            "anchor_children_add",
+            |event| Box::pin(async move {
+                let anchor = event.gadget.get().unwrap();
+                layout_watch_child(&anchor, &event.new_value, anchor_arrange_boxed).await;
+                anchor_arrange_child(&anchor, &event.new_value).await;
+            })).await;
+
+        gadget.children.listen_remove(
+            "anchor_children_remove",
+            |event| Box::pin(async move {
+                layout_unwatch_child(&event.old_value).await;
+            })).await;
+
+        layout_arrange_on(&gadget.dim, "anchor_arrange", anchor_arrange_boxed, false).await;
+        layout_arrange_on(&gadget.padding, "anchor_arrange", anchor_arrange_boxed, false).await;
+
+        gadget
+    }
+
+    /// Attaches anchors to a child, placing it again within its container.
+    pub async fn set_anchors(child: &Gadget, anchors: Anchors) {
+        child.values.set(ANCHOR_KEY.to_string(), Arbitrary::new(anchors)).await;
+    }
+
+    pub async fn anchors(child: &Gadget) -> Option<Anchors> {
+        child.values.get(&ANCHOR_KEY.to_string()).await?
+            .get::<Anchors>()
+            .copied()
+    }
+}
+
+fn anchor_arrange_boxed(anchor: Gadget) -> Pin<Box<dyn Future<Output=()> + Send + Sync>> {
+    Box::pin(anchor_arrange(anchor))
+}
+
+async fn anchor_arrange(anchor: Gadget) {
+    let children = anchor.children.get_vec().await.clone();
+    for child in children.iter() {
+        anchor_arrange_child(&anchor, child).await;
+    }
+}
+
+async fn anchor_arrange_child(anchor: &Gadget, child: &Gadget) {
+    let anchors = match AnchorLayout::anchors(child).await {
+        // Absolutely positioned
+        None => return,
+        Some(anchors) => anchors,
+    };
+    let content = anchor.content_region().await;
+    let dim = content.size;
+    let preferred = child.measure(Constraints::loose(dim)).await;
+    let (x, width) = anchors.horizontal.resolve(preferred.x, dim.x);
+    let (y, height) = anchors.vertical.resolve(preferred.y, dim.y);
+    layout_place(child, Region::from_origin_size(
+        content.origin + ScalarPair::new(x, y), (width, height))).await;
+}
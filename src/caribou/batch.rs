@@ -1,9 +1,10 @@
 
 use std::fmt::{Debug};
 use std::ops::{Deref, DerefMut};
+use std::sync::Arc;
 
 
-use crate::caribou::math::{Scalar, ScalarPair};
+use crate::caribou::math::{Region, Scalar, ScalarPair};
 use crate::caribou::state::Arbitrary;
 use crate::caribou::text::Font;
 
@@ -90,7 +91,7 @@ pub fn begin_paint() -> Painting {
 
 impl Painting {
     pub fn finish(self) -> Batch {
-        Batch { ops: self.ops }
+        Batch { ops: Arc::new(self.ops) }
     }
 
     pub fn path(self, transform: Transform, path: Path, brush: Brush) -> Self {
@@ -142,10 +143,12 @@ impl Painting {
     }
 }
 
+/// Ops are shared between clones, so composing a child batch into its parent is cheap and only
+/// the batch being modified gets copied.
 #[repr(transparent)]
 #[derive(Debug, Default, Clone, PartialEq)]
 pub struct Batch {
-    ops: Vec<BatchOp>,
+    ops: Arc<Vec<BatchOp>>,
 }
 
 impl Deref for Batch {
@@ -158,17 +161,27 @@ impl Deref for Batch {
 
 impl DerefMut for Batch {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.ops
+        Arc::make_mut(&mut self.ops)
     }
 }
 
 impl Batch {
     pub fn batch_append(&mut self, other: Batch) {
-        self.ops.extend(other.ops);
+        Arc::make_mut(&mut self.ops).extend(other.unwrap());
     }
 
     pub fn unwrap(self) -> Vec<BatchOp> {
-        self.ops
+        Arc::try_unwrap(self.ops).unwrap_or_else(|ops| (*ops).clone())
+    }
+
+    /// Whether both batches share the same ops, i.e. one is an unmodified clone of the other.
+    pub fn ptr_eq(&self, other: &Batch) -> bool {
+        Arc::ptr_eq(&self.ops, &other.ops)
+    }
+
+    /// Identity of the shared ops, stable for as long as any clone of this batch is alive.
+    pub fn identity(&self) -> usize {
+        Arc::as_ptr(&self.ops) as usize
     }
 }
 
@@ -226,6 +239,7 @@ pub struct Transform {
     pub rotate: Scalar,
     pub rotate_center: ScalarPair,
     pub clip: Option<ScalarPair>,
+    pub opacity: Scalar,
 }
 
 impl Default for Transform {
@@ -236,6 +250,7 @@ impl Default for Transform {
             rotate: Scalar::default(),
             rotate_center: ScalarPair::zero(),
             clip: None,
+            opacity: 1.0,
         }
     }
 }
@@ -296,6 +311,135 @@ impl Transform {
             ..*self
         }
     }
+
+    pub fn from_opacity<S: Into<Scalar>>(opacity: S) -> Self {
+        Self {
+            opacity: opacity.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn opacity<S: Into<Scalar>>(&self, opacity: S) -> Self {
+        Self {
+            opacity: self.opacity * opacity.into(),
+            ..*self
+        }
+    }
+
+    /// Maps a point from the transformed space into the outer space.
+    pub fn map_point<S: Into<ScalarPair>>(&self, point: S) -> ScalarPair {
+        let point = point.into();
+        let (sin, cos) = self.rotate.to_radians().sin_cos();
+        let rel = point - self.rotate_center;
+        let rotated = ScalarPair::new(rel.x * cos - rel.y * sin,
+                                      rel.x * sin + rel.y * cos);
+        self.translate + (self.rotate_center + rotated).element_wise_mul(self.scale)
+    }
+
+    /// Inverse of [`Transform::map_point`], `None` if the transform is degenerate.
+    pub fn unmap_point<S: Into<ScalarPair>>(&self, point: S) -> Option<ScalarPair> {
+        if self.scale.x == 0.0 || self.scale.y == 0.0 {
+            return None;
+        }
+        let point = point.into() - self.translate;
+        let unscaled = ScalarPair::new(point.x / self.scale.x, point.y / self.scale.y);
+        let (sin, cos) = self.rotate.to_radians().sin_cos();
+        let rel = unscaled - self.rotate_center;
+        let rotated = ScalarPair::new(rel.x * cos + rel.y * sin,
+                                      -rel.x * sin + rel.y * cos);
+        Some(self.rotate_center + rotated)
+    }
+
+    /// Bounding box of a region mapped into the outer space.
+    pub fn map_region<R: Into<Region>>(&self, region: R) -> Region {
+        let region = region.into();
+        let begin = region.origin;
+        let end = region.origin + region.size;
+        let corners = [
+            self.map_point(begin),
+            self.map_point((end.x, begin.y)),
+            self.map_point((begin.x, end.y)),
+            self.map_point(end),
+        ];
+        let min = corners.iter().fold(corners[0], |min, p|
+            ScalarPair::new(min.x.min(p.x), min.y.min(p.y)));
+        let max = corners.iter().fold(corners[0], |max, p|
+            ScalarPair::new(max.x.max(p.x), max.y.max(p.y)));
+        Region::from_begin_end(min, max)
+    }
+}
+
+/// Per-gadget transform applied on top of its position when composed into the parent batch.
+/// The origin is relative to the gadget's dimension, e.g. `(0.5, 0.5)` is the center.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderTransform {
+    pub scale: ScalarPair,
+    pub rotate: Scalar,
+    pub origin: ScalarPair,
+}
+
+impl Default for RenderTransform {
+    fn default() -> Self {
+        Self {
+            scale: ScalarPair::one(),
+            rotate: Scalar::default(),
+            origin: ScalarPair::zero(),
+        }
+    }
+}
+
+impl RenderTransform {
+    pub fn from_scale<S: Into<ScalarPair>>(scale: S) -> Self {
+        Self {
+            scale: scale.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn from_rotate<S: Into<Scalar>>(rotate: S) -> Self {
+        Self {
+            rotate: rotate.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn scale<S: Into<ScalarPair>>(self, scale: S) -> Self {
+        Self {
+            scale: scale.into(),
+            ..self
+        }
+    }
+
+    pub fn rotate<S: Into<Scalar>>(self, rotate: S) -> Self {
+        Self {
+            rotate: rotate.into(),
+            ..self
+        }
+    }
+
+    pub fn origin<S: Into<ScalarPair>>(self, origin: S) -> Self {
+        Self {
+            origin: origin.into(),
+            ..self
+        }
+    }
+
+    pub fn is_identity(&self) -> bool {
+        self.scale == ScalarPair::one() && self.rotate == 0.0
+    }
+
+    /// Builds the transform placing a gadget of `dim` at `pos`, scaling and rotating it
+    /// around its origin.
+    pub fn compose(&self, pos: ScalarPair, dim: ScalarPair) -> Transform {
+        let origin = self.origin.element_wise_mul(dim);
+        Transform {
+            translate: pos + origin - origin.element_wise_mul(self.scale),
+            scale: self.scale,
+            rotate: self.rotate,
+            rotate_center: origin,
+            ..Default::default()
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -493,4 +637,54 @@ impl Colors {
     pub const GRAY_LIGHT: SolidColor = SolidColor { r: 0.75, g: 0.75, b: 0.75, a: 1.0 };
     pub const GRAY_DARK: SolidColor = SolidColor { r: 0.25, g: 0.25, b: 0.25, a: 1.0 };
     pub const TRANSPARENT: SolidColor = SolidColor { r: 0.0, g: 0.0, b: 0.0, a: 0.0 };
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(actual: ScalarPair, expected: ScalarPair) {
+        assert!((actual.x - expected.x).abs() < 1e-4 && (actual.y - expected.y).abs() < 1e-4,
+                "{:?} != {:?}", actual, expected);
+    }
+
+    #[test]
+    fn unmap_inverts_map() {
+        let transform = Transform {
+            rotate_center: ScalarPair::new(5.0, 5.0),
+            ..Transform::from_translate((10.0, 20.0)).scale((2.0, 0.5)).rotate(30.0)
+        };
+        for point in [(0.0, 0.0), (5.0, 5.0), (-3.0, 12.5), (100.0, -40.0)] {
+            let point = ScalarPair::from(point);
+            assert_near(transform.unmap_point(transform.map_point(point)).unwrap(), point);
+        }
+    }
+
+    #[test]
+    fn compose_scales_and_rotates_around_origin() {
+        let transform = RenderTransform::from_scale((2.0, 2.0))
+            .rotate(90.0)
+            .origin((0.5, 0.5))
+            .compose(ScalarPair::new(10.0, 10.0), ScalarPair::new(20.0, 40.0));
+        // The origin stays where it is without the render transform
+        assert_near(transform.map_point((10.0, 20.0)), ScalarPair::new(20.0, 30.0));
+        // Right of the origin ends up below it, twice as far
+        assert_near(transform.map_point((20.0, 20.0)), ScalarPair::new(20.0, 50.0));
+        assert_near(transform.unmap_point((20.0, 50.0)).unwrap(), ScalarPair::new(20.0, 20.0));
+    }
+
+    #[test]
+    fn compose_without_render_transform_translates() {
+        let transform = RenderTransform::default()
+            .compose(ScalarPair::new(10.0, 10.0), ScalarPair::new(20.0, 40.0));
+        assert_near(transform.map_point((1.0, 2.0)), ScalarPair::new(11.0, 12.0));
+        assert_near(transform.unmap_point((11.0, 12.0)).unwrap(), ScalarPair::new(1.0, 2.0));
+    }
+
+    #[test]
+    fn degenerate_scale_has_no_inverse() {
+        assert_eq!(Transform::from_scale((0.0, 1.0)).unmap_point((1.0, 1.0)), None);
+        let transform = RenderTransform::from_scale((2.0, 0.0))
+            .compose(ScalarPair::new(10.0, 10.0), ScalarPair::new(20.0, 40.0));
+        assert_eq!(transform.unmap_point((10.0, 10.0)), None);
+    }
+}
@@ -6,7 +6,7 @@ use std::future::Future;
 use crate::caribou::input::MouseButton;
 use crate::caribou::math::ScalarPair;
 
-pub type PinnedFutureBox<R> = Pin<Box<dyn Future<Output=R>>>;
+pub type PinnedFutureBox<R> = Pin<Box<dyn Future<Output=R> + Send + Sync>>;
 pub type Listener<P, R> = Arc<dyn Fn(P) -> PinnedFutureBox<R>  + Send + Sync>;
 
 pub struct Event<P: Send + Clone = (), R: Send + Clone = ()> {
@@ -0,0 +1,319 @@
+use std::future::Future;
+use std::pin::Pin;
+use crate::caribou::gadget::Gadget;
+use crate::caribou::layout::{Alignment, Layout, layout_arrange_on, layout_place,
+                             layout_unwatch_child, layout_watch_child, Orientation};
+use crate::caribou::math::{Region, Scalar, ScalarPair};
+use crate::caribou::measure::Constraints;
+use crate::caribou::state::{Arbitrary, State};
+
+/// Container laying its children out in lines like CSS flexbox. Per-child parameters are the
+/// [`FlexItem`] attached to the child with [`Flex::set_item`].
+pub struct Flex;
+
+/// Distribution of the free space left on a line along the main axis.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Justify {
+    #[default]
+    Start,
+    Center,
+    End,
+    SpaceBetween,
+    SpaceAround,
+    SpaceEvenly,
+}
+
+/// Attached flex parameters of a child.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FlexItem {
+    /// Share of the free space taken when the line is longer than its items.
+    pub grow: Scalar,
+    /// Share of the overflow given up when the line is shorter than its items, weighted by the
+    /// basis.
+    pub shrink: Scalar,
+    /// Main-axis size before growing or shrinking, the measured size if `None`.
+    pub basis: Option<Scalar>,
+    /// Overrides the container's `align_items` for this child.
+    pub align_self: Option<Alignment>,
+}
+
+impl Default for FlexItem {
+    fn default() -> Self {
+        Self {
+            grow: 0.0,
+            shrink: 1.0,
+            basis: None,
+            align_self: None,
+        }
+    }
+}
+
+impl FlexItem {
+    pub fn grow(self, grow: Scalar) -> Self {
+        Self { grow, ..self }
+    }
+
+    pub fn shrink(self, shrink: Scalar) -> Self {
+        Self { shrink, ..self }
+    }
+
+    pub fn basis(self, basis: Scalar) -> Self {
+        Self { basis: Some(basis), ..self }
+    }
+
+    pub fn align_self(self, align_self: Alignment) -> Self {
+        Self { align_self: Some(align_self), ..self }
+    }
+}
+
+const FLEX_ITEM_KEY: &str = "flex.item";
+
+impl Flex {
+    pub async fn create(direction: Orientation) -> Gadget {
+        let gadget = Layout::create().await;
+
+        // Fill specialized data
+        let data = FlexData {
+            direction: State::new(gadget.refer(), direction),
+            wrap: State::new(gadget.refer(), false),
+            justify: State::new(gadget.refer(), Justify::Start),
+            align_items: State::new(gadget.refer(), Alignment::Stretch),
+            gap: State::new(gadget.refer(), 0.0),
+        };
+        gadget.data.set_any(data.clone()).await;
+
+        // Fill common properties
+        gadget.type_name.set("Flex").await;
+        gadget.set_measurer(|flex, constraints| Box::pin(async move {
+            flex_measure(flex, constraints).await
+        })).await;
+
+        // Listen re-layout
+        gadget.children.listen_add(
+            "flex_children_add",
+            |event| Box::pin(async move {
+                let flex = event.gadget.get().unwrap();
+                layout_watch_child(&flex, &event.new_value, flex_arrange_boxed).await;
+                flex.invalidate_measure().await;
+                flex_arrange(flex).await;
+            })).await;
+
+        gadget.children.listen_remove(
+            "flex_children_remove",
+            |event| Box::pin(async move {
+                let flex = event.gadget.get().unwrap();
+                layout_unwatch_child(&event.old_value).await;
+                flex.invalidate_measure().await;
+                flex_arrange(flex).await;
+            })).await;
+
+        layout_arrange_on(&gadget.dim, "flex_arrange", flex_arrange_boxed, false).await;
+        layout_arrange_on(&gadget.padding, "flex_arrange", flex_arrange_boxed, true).await;
+        layout_arrange_on(&data.direction, "flex_arrange", flex_arrange_boxed, true).await;
+        layout_arrange_on(&data.wrap, "flex_arrange", flex_arrange_boxed, true).await;
+        layout_arrange_on(&data.justify, "flex_arrange", flex_arrange_boxed, false).await;
+        layout_arrange_on(&data.align_items, "flex_arrange", flex_arrange_boxed, false).await;
+        layout_arrange_on(&data.gap, "flex_arrange", flex_arrange_boxed, true).await;
+
+        gadget
+    }
+
+    /// Attaches flex parameters to a child, re-arranging its container.
+    pub async fn set_item(child: &Gadget, item: FlexItem) {
+        child.values.set(FLEX_ITEM_KEY.to_string(), Arbitrary::new(item)).await;
+    }
+
+    pub async fn item(child: &Gadget) -> FlexItem {
+        child.values.get(&FLEX_ITEM_KEY.to_string()).await
+            .and_then(|item| item.get::<FlexItem>().copied())
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Clone)]
+pub struct FlexData {
+    pub direction: State<Orientation>,
+    pub wrap: State<bool>,
+    pub justify: State<Justify>,
+    pub align_items: State<Alignment>,
+    /// Space between items on a line, and between lines.
+    pub gap: State<Scalar>,
+}
+
+pub async fn flex_data(flex: &Gadget) -> FlexData {
+    let data = flex.data.get_cloned().await;
+    let data = data.get::<FlexData>().await;
+    (*data).clone()
+}
+
+struct FlexEntry {
+    child: Gadget,
+    item: FlexItem,
+    basis: Scalar,
+    cross: Scalar,
+    main: Scalar,
+    min: Scalar,
+    max: Scalar,
+}
+
+async fn flex_entries(flex: &Gadget, direction: Orientation, cross_available: Scalar)
+    -> Vec<FlexEntry>
+{
+    let child_constraints = Constraints::loose(
+        direction.pair(Scalar::INFINITY, cross_available));
+    let children = flex.children.get_vec().await.clone();
+    let mut entries = Vec::with_capacity(children.len());
+    for child in children {
+        let item = Flex::item(&child).await;
+        let desired = child.measure(child_constraints).await;
+        let own = child.own_constraints().await;
+        let basis = item.basis.unwrap_or_else(|| direction.main(desired)).max(0.0);
+        entries.push(FlexEntry {
+            child,
+            item,
+            basis,
+            cross: direction.cross(desired),
+            main: basis,
+            min: direction.main(own.min),
+            max: direction.main(own.max),
+        });
+    }
+    entries
+}
+
+/// Items at their basis on a single line, or the widest item on the main axis when wrapping.
+async fn flex_measure(flex: Gadget, constraints: Constraints) -> ScalarPair {
+    let data = flex_data(&flex).await;
+    let direction = data.direction.get_cloned().await;
+    let wrap = data.wrap.get_cloned().await;
+    let gap = data.gap.get_cloned().await;
+    let padding = flex.padding.get_cloned().await;
+    let constraints = constraints.shrink(padding.size());
+    let entries = flex_entries(&flex, direction, direction.cross(constraints.max)).await;
+    let cross = entries.iter().map(|entry| entry.cross).fold(0.0, Scalar::max);
+    let main = if wrap {
+        entries.iter().map(|entry| entry.basis).fold(0.0, Scalar::max)
+    } else {
+        entries.iter().map(|entry| entry.basis).sum::<Scalar>()
+            + gap * entries.len().saturating_sub(1) as Scalar
+    };
+    direction.pair(main, cross) + padding.size()
+}
+
+fn flex_arrange_boxed(flex: Gadget) -> Pin<Box<dyn Future<Output=()> + Send + Sync>> {
+    Box::pin(flex_arrange(flex))
+}
+
+async fn flex_arrange(flex: Gadget) {
+    let data = flex_data(&flex).await;
+    let direction = data.direction.get_cloned().await;
+    let wrap = data.wrap.get_cloned().await;
+    let justify = data.justify.get_cloned().await;
+    let align_items = data.align_items.get_cloned().await;
+    let gap = data.gap.get_cloned().await;
+    let content = flex.content_region().await;
+    let dim = content.size;
+    let available = direction.main(dim);
+
+    let mut entries = flex_entries(&flex, direction, direction.cross(dim)).await;
+
+    // Break into lines
+    let mut lines: Vec<&mut [FlexEntry]> = Vec::new();
+    let mut rest = entries.as_mut_slice();
+    while !rest.is_empty() {
+        let mut count = 1;
+        if wrap {
+            let mut length = rest[0].basis;
+            while count < rest.len() && length + gap + rest[count].basis <= available {
+                length += gap + rest[count].basis;
+                count += 1;
+            }
+        } else {
+            count = rest.len();
+        }
+        let (line, tail) = rest.split_at_mut(count);
+        lines.push(line);
+        rest = tail;
+    }
+
+    let line_count = lines.len();
+    let mut cross_offset = 0.0;
+    for line in lines {
+        let gaps = gap * (line.len() - 1) as Scalar;
+        let free = available - gaps - line.iter().map(|entry| entry.basis).sum::<Scalar>();
+        flex_resolve_main(line, free);
+        let used = gaps + line.iter().map(|entry| entry.main).sum::<Scalar>();
+        // A single line takes the whole cross axis, wrapped lines as much as their largest item
+        let line_cross = if line_count == 1 {
+            direction.cross(dim)
+        } else {
+            line.iter().map(|entry| entry.cross).fold(0.0, Scalar::max)
+        };
+
+        let (mut offset, spacing) = flex_justify(justify, (available - used).max(0.0), line.len());
+        for entry in line.iter() {
+            let alignment = entry.item.align_self.unwrap_or(align_items);
+            let (cross_pos, cross) = alignment.place(entry.cross, line_cross);
+            let pos: ScalarPair = direction.pair(offset, cross_offset + cross_pos);
+            layout_place(&entry.child, Region::from_origin_size(
+                content.origin + pos, direction.pair(entry.main, cross))).await;
+            offset += entry.main + gap + spacing;
+        }
+        cross_offset += line_cross + gap;
+    }
+}
+
+/// Grows or shrinks the items of a line to take up `free`, which is negative on overflow.
+/// Items reaching their `min_dim` or `max_dim` are frozen there and the rest is distributed
+/// among the others; whatever cannot be absorbed overflows and gets clipped.
+fn flex_resolve_main(line: &mut [FlexEntry], free: Scalar) {
+    let mut frozen = vec![false; line.len()];
+    for entry in line.iter_mut() {
+        entry.main = entry.basis.clamp(entry.min, entry.max.max(entry.min));
+    }
+    loop {
+        let left = free + line.iter().map(|entry| entry.basis - entry.main).sum::<Scalar>();
+        let weight = |entry: &FlexEntry| if free > 0.0 {
+            entry.item.grow
+        } else {
+            entry.item.shrink * entry.basis
+        };
+        let total: Scalar = line.iter().zip(frozen.iter())
+            .filter(|(_, frozen)| !**frozen)
+            .map(|(entry, _)| weight(entry))
+            .sum();
+        if free == 0.0 || total <= 0.0 {
+            return;
+        }
+        let mut clamped = false;
+        for (entry, frozen) in line.iter_mut().zip(frozen.iter_mut()) {
+            if *frozen {
+                continue;
+            }
+            let target = entry.main + left * weight(entry) / total;
+            let max = entry.max.max(entry.min);
+            entry.main = target.clamp(entry.min, max);
+            if entry.main != target {
+                *frozen = true;
+                clamped = true;
+            }
+        }
+        if !clamped {
+            return;
+        }
+    }
+}
+
+/// Offset of the first item and extra space between items for the free space left on a line.
+fn flex_justify(justify: Justify, free: Scalar, count: usize) -> (Scalar, Scalar) {
+    let count = count as Scalar;
+    match justify {
+        Justify::Start => (0.0, 0.0),
+        Justify::Center => (free / 2.0, 0.0),
+        Justify::End => (free, 0.0),
+        Justify::SpaceBetween if count > 1.0 => (0.0, free / (count - 1.0)),
+        Justify::SpaceBetween => (0.0, 0.0),
+        Justify::SpaceAround => (free / count / 2.0, free / count),
+        Justify::SpaceEvenly => (free / (count + 1.0), free / (count + 1.0)),
+    }
+}
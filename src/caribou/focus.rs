@@ -6,10 +6,25 @@ use async_recursion::async_recursion;
 use log::debug;
 use tokio::sync::RwLock;
 use crate::caribou::gadget::{Gadget, GadgetParent, GadgetRef};
-use crate::caribou::input::Key;
+use crate::caribou::input::{Key, KeyEvent};
 use crate::caribou::state::{OptionalState, State};
 use crate::caribou::window::{Window, WindowRef};
 
+/// Handles a key pressed while a gadget or one of its descendants is focused, returning whether
+/// it was consumed. Consumed keys are neither given to the focused gadget nor cycle the focus.
+pub type KeyHandler = Arc<dyn Fn(Gadget, KeyEvent)
+    -> Pin<Box<dyn Future<Output=bool> + Send + Sync>> + Send + Sync>;
+
+impl Gadget {
+    pub async fn set_key_handler<F>(&self, handler: F)
+    where
+        F: Fn(Gadget, KeyEvent) -> Pin<Box<dyn Future<Output=bool> + Send + Sync>>
+            + Send + Sync + 'static,
+    {
+        self.key_handler.set(Some(Arc::new(handler))).await;
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FocusEventInfo {
     Gain,
@@ -37,46 +52,55 @@ impl CaribouFocus {
     pub async fn attach_tab_listener(&self, window: &Window) {
         *self.window_ref.write().await = Some(window.refer());
         let wr = window.refer();
-        window.key_down.listen_add(
-            "cb_focus",
-            move |event| {
-                let window = wr.get().unwrap();
-                Box::pin(async move {
-                    let key = event.new_value;
-                    let focus = window.cb_focus.borrow();
-                    if key == Key::Tab {
+        window.on_key.listen(move |event| {
+            let window = wr.get().unwrap();
+            Box::pin(async move {
+                let focus = window.cb_focus.borrow();
+                if event.is_down && focus.handle_key(event.clone()).await {
+                    return;
+                }
+                if event.key == Key::Tab {
+                    if event.is_down {
                         focus.cycle().await;
-                    } else {
-                        let focused = focus.focused.get().await;
-                        if let Some(focused) = focused {
-                            if let Some(focused) = focused.get() {
-                                focused.key_down.push(key).await;
-                            }
-                        }
                     }
-                })
-            }
-        ).await;
+                    return;
+                }
+                let focused = match focus.focused.get().await.and_then(|gr| gr.get()) {
+                    None => return,
+                    Some(focused) => focused,
+                };
+                if !event.is_down {
+                    focused.key_down.remove(&event.key).await;
+                } else if !event.repeat && !focused.key_down.get_vec().await.contains(&event.key) {
+                    focused.key_down.push(event.key).await;
+                }
+                focused.on_key.emit(event).await;
+            })
+        }).await;
+    }
 
-        let wr = window.refer();
-        window.key_down.listen_remove(
-            "cb_focus",
-            move |event| {
-                let window = wr.get().unwrap();
-                Box::pin(async move {
-                    let key = event.old_value;
-                    let focused = window.cb_focus
-                        .focused.get().await;
-                    if key != Key::Tab {
-                        if let Some(focused) = focused {
-                            if let Some(focused) = focused.get() {
-                                focused.key_down.remove(&key).await;
-                            }
-                        }
-                    }
-                })
+    /// Gives a pressed key to the key handlers of the focused gadget and its ancestors,
+    /// innermost first, until one consumes it. Returns whether any did.
+    async fn handle_key(&self, event: KeyEvent) -> bool {
+        let mut cur = match self.focused.get().await.and_then(|gr| gr.get()) {
+            None => return false,
+            Some(focused) => focused,
+        };
+        loop {
+            let handler = cur.key_handler.get_cloned().await;
+            if let Some(handler) = handler {
+                if handler(cur.clone(), event.clone()).await {
+                    return true;
+                }
             }
-        ).await;
+            cur = match cur.parent.get_cloned().await {
+                GadgetParent::Gadget(gr) => match gr.get() {
+                    None => return false,
+                    Some(parent) => parent,
+                },
+                _ => return false,
+            };
+        }
     }
 
     async fn focus_locked(&self) -> bool {
@@ -99,6 +123,8 @@ impl CaribouFocus {
                     debug!("Focused gadget no longer exists");
                 }
                 Some(gadget) => {
+                    // Keys held down are let go with the focus, their releases going elsewhere
+                    gadget.key_down.clear().await;
                     gadget.focused.set(false).await;
                     debug!("Cleared focus");
                 }
@@ -106,6 +132,13 @@ impl CaribouFocus {
         }
     }
 
+    /// Moves the focus to a gadget, even when the focused one locks it.
+    pub async fn focus(&self, gadget: &Gadget) {
+        self.clear_focus().await;
+        self.focused.put(gadget.refer()).await;
+        gadget.focused.set(true).await;
+    }
+
     pub async fn cycle(&self) {
         // The focus subsystem (CBF) is available in 2 modes:
         // * User provides a MANUAL tab order thus the dispatch process relies on it
@@ -218,6 +251,9 @@ async fn focus_propagate(gadget: &Gadget, from: usize) -> Option<Gadget> {
         }
         let child = top.children[top.index].clone();
         top.index += 1;
+        if !child.visible.get_cloned().await {
+            continue;
+        }
         if child.accept_focus.get_cloned().await {
             return Some(child);
         }
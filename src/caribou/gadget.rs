@@ -3,10 +3,16 @@ use std::ops::Deref;
 use std::sync::{Arc, Weak};
 
 
-use crate::caribou::batch::{Batch, Brush};
+use crate::caribou::batch::{Batch, Brush, RenderTransform};
 
-use crate::caribou::input::{DragInfo, Key, MouseButton};
-use crate::caribou::math::ScalarPair;
+use crate::caribou::focus::KeyHandler;
+use crate::caribou::event::Event;
+use crate::caribou::input::{CursorIcon, DragInfo, Key, KeyEvent, Modifier2, MouseButton, MouseEvent};
+use crate::caribou::layout::layout_child_transform;
+use crate::caribou::math::{Insets, Region, Scalar, ScalarPair};
+use crate::caribou::measure::Measurer;
+use crate::caribou::scroll::ScrollHandler;
+use crate::caribou::tooltip::Tooltip;
 use crate::caribou::state::{Arbitrary, MutableArbitrary, OptionalState, State, StateMap, StateVec};
 use crate::caribou::window::WindowRef;
 use crate::cb_backend_skia_gl::skia_font_default_cjk;
@@ -60,17 +66,36 @@ impl GadgetRef {
 }
 
 pub struct GadgetInner {
+    // Identity
+    pub type_name: State<&'static str>,
+    pub id: State<Option<String>>,
+    pub classes: StateVec<String>,
     // Common
     pub pos: State<ScalarPair>,
     pub dim: State<ScalarPair>,
     pub enabled: State<bool>,
+    /// Invisible gadgets keep their place among their siblings but neither paint nor receive
+    /// input or focus.
+    pub visible: State<bool>,
+    // Measuring
+    pub min_dim: State<ScalarPair>,
+    pub max_dim: State<ScalarPair>,
+    pub margin: State<Insets>,
+    pub padding: State<Insets>,
+    pub measurer: State<Option<Measurer>>,
+    pub measure_generation: State<usize>,
     // Hierarchy
     pub parent: State<GadgetParent>,
     pub children: StateVec<Gadget>,
+    pub attached_window: OptionalState<WindowRef>,
     // Appearance
     pub brush: State<Brush>,
     pub font: State<Arbitrary>,
     pub batch: State<Batch>,
+    pub damage: StateVec<Region>,
+    pub render_transform: State<RenderTransform>,
+    pub opacity: State<Scalar>,
+    pub tooltip: State<Option<Tooltip>>,
     // Focusing
     pub propagate: State<bool>,
     pub accept_focus: State<bool>,
@@ -83,7 +108,15 @@ pub struct GadgetInner {
     pub mouse_down: StateVec<MouseButton>,
     pub mouse_pos: OptionalState<ScalarPair>,
     pub mouse_drag: StateVec<DragInfo>,
+    /// Tells `MouseEvent::Enter` and `MouseEvent::Leave` once each time the pointer is given to
+    /// or taken from the gadget, and every move and button in between with the modifiers held.
+    pub on_mouse: Event<MouseEvent>,
     pub key_down: StateVec<Key>,
+    /// Tells every key event given to the gadget while it is focused.
+    pub on_key: Event<KeyEvent>,
+    pub key_handler: State<Option<KeyHandler>>,
+    pub scroll_handler: State<Option<ScrollHandler>>,
+    pub cursor: State<Option<CursorIcon>>,
     // Textual
     pub accept_text: State<bool>,
     pub pre_edit: State<String>,
@@ -124,6 +157,91 @@ impl Gadget {
         }
     }
 
+    /// Maps a point in this gadget's space into the space of its window, `None` if the gadget
+    /// is not attached to one.
+    pub async fn map_to_window(&self, point: ScalarPair) -> Option<ScalarPair> {
+        let mut point = point;
+        let mut current = self.clone();
+        loop {
+            match current.parent.get_cloned().await {
+                GadgetParent::None => return None,
+                GadgetParent::Gadget(parent) => {
+                    point = layout_child_transform(&current).await.map_point(point);
+                    current = parent.get()?;
+                }
+                GadgetParent::Window(window) => {
+                    // The root is drawn as is, everything else hosted by the window is an overlay
+                    let window = window.get()?;
+                    if window.root.get_cloned().await != current {
+                        point = layout_child_transform(&current).await.map_point(point);
+                    }
+                    return Some(point);
+                }
+            }
+        }
+    }
+
+    /// The part of this gadget inside its padding, where its content goes.
+    pub async fn content_region(&self) -> Region {
+        let dim = self.dim.get_cloned().await;
+        self.padding.get_cloned().await
+            .shrink(Region::from_origin_size(ScalarPair::zero(), dim))
+    }
+
+    /// Bounding box of this gadget in the space of its window.
+    pub async fn window_region(&self) -> Option<Region> {
+        let dim = self.dim.get_cloned().await;
+        let corners = [
+            ScalarPair::zero(),
+            ScalarPair::new(dim.x, 0.0),
+            ScalarPair::new(0.0, dim.y),
+            dim,
+        ];
+        let mut begin = ScalarPair::new(Scalar::INFINITY, Scalar::INFINITY);
+        let mut end = ScalarPair::new(Scalar::NEG_INFINITY, Scalar::NEG_INFINITY);
+        for corner in corners {
+            let mapped = self.map_to_window(corner).await?;
+            begin = ScalarPair::new(begin.x.min(mapped.x), begin.y.min(mapped.y));
+            end = ScalarPair::new(end.x.max(mapped.x), end.y.max(mapped.y));
+        }
+        Some(Region::from_begin_end(begin, end))
+    }
+
+    /// The cursor of this gadget, or of the nearest ancestor that has one.
+    pub async fn resolve_cursor(&self) -> CursorIcon {
+        let mut current = self.clone();
+        loop {
+            if let Some(cursor) = current.cursor.get_cloned().await {
+                return cursor;
+            }
+            current = match current.parent.get_cloned().await {
+                GadgetParent::Gadget(parent) => match parent.get() {
+                    None => return CursorIcon::Default,
+                    Some(parent) => parent,
+                },
+                _ => return CursorIcon::Default,
+            };
+        }
+    }
+
+    /// Marks a region of this gadget, in its own space, as changed so that only that part gets
+    /// redrawn. Reported before setting the new batch; a batch change with nothing reported is
+    /// taken as a change of the whole gadget.
+    pub async fn report_damage(&self, region: Region) {
+        let mut damage = self.damage.get_vec_mut().await;
+        damage.push(region);
+        // Nobody may be composing this gadget, keep the list from growing without bound
+        if damage.len() > GADGET_DAMAGE_LIMIT {
+            let union = damage.iter().skip(1).fold(damage[0], |acc, r| acc.union(*r));
+            *damage = vec![union];
+        }
+    }
+
+    /// Takes the regions reported since the last call.
+    pub async fn take_damage(&self) -> Vec<Region> {
+        std::mem::take(&mut *self.damage.get_vec_mut().await)
+    }
+
     pub async fn is_focused(&self) -> bool {
         let window = match self.get_window().await {
             None => return false,
@@ -143,20 +261,114 @@ impl Gadget {
     }
 }
 
+const GADGET_DAMAGE_LIMIT: usize = 16;
+
+/// Modifiers held now in the window of a gadget, none if it is not attached to one.
+pub async fn gadget_modifiers(gadget: &Gadget) -> Modifier2 {
+    match gadget.get_window().await.and_then(|wr| wr.get()) {
+        None => Modifier2::empty(),
+        Some(window) => window.modifiers.get_cloned().await,
+    }
+}
+
+/// Gives the pointer to a gadget at a point in its space, telling it `MouseEvent::Enter` if it
+/// did not have it, then `MouseEvent::Move`.
+pub async fn gadget_pointer_put(gadget: &Gadget, pos: ScalarPair) {
+    if gadget.mouse_pos.replace(Some(pos)).await.is_none() {
+        gadget.on_mouse.emit(MouseEvent::Enter).await;
+    }
+    let modifiers = gadget_modifiers(gadget).await;
+    gadget.on_mouse.emit(MouseEvent::Move { position: pos, modifiers }).await;
+}
+
+/// Presses a button over a gadget having the pointer, telling it `MouseEvent::Button`.
+pub async fn gadget_button_push(gadget: &Gadget, button: MouseButton) {
+    gadget.mouse_down.push(button).await;
+    gadget_button_emit(gadget, button, true).await;
+}
+
+/// Releases a button pressed over a gadget, telling it `MouseEvent::Button` if it was.
+pub async fn gadget_button_remove(gadget: &Gadget, button: MouseButton) {
+    if gadget.mouse_down.remove(&button).await.is_some() {
+        gadget_button_emit(gadget, button, false).await;
+    }
+}
+
+async fn gadget_button_emit(gadget: &Gadget, button: MouseButton, is_down: bool) {
+    let position = gadget.mouse_pos.get().await.unwrap_or_default();
+    let modifiers = gadget_modifiers(gadget).await;
+    gadget.on_mouse.emit(MouseEvent::Button { position, button, is_down, modifiers }).await;
+}
+
+/// Takes the pointer and the buttons pressed over it from a gadget, telling it
+/// `MouseEvent::Leave` if it had it.
+pub async fn gadget_pointer_take(gadget: &Gadget) {
+    if gadget.mouse_pos.is_set().await {
+        gadget.mouse_down.clear().await;
+    }
+    if gadget.mouse_pos.replace(None).await.is_some() {
+        gadget.on_mouse.emit(MouseEvent::Leave).await;
+    }
+}
+
+/// Brings `attached_window` of the whole subtree in line with `window`, taking it children
+/// first and putting it parent first.
+pub async fn gadget_update_attachment(gadget: &Gadget, window: Option<WindowRef>) {
+    if gadget.attached_window.get().await == window {
+        return;
+    }
+    let subtree = gadget_subtree(gadget).await;
+    for node in subtree.iter().rev() {
+        node.attached_window.take().await;
+    }
+    if let Some(window) = window {
+        for node in subtree.iter() {
+            node.attached_window.put(window.clone()).await;
+        }
+    }
+}
+
+/// Collects a gadget and all of its descendants in pre-order.
+pub async fn gadget_subtree(gadget: &Gadget) -> Vec<Gadget> {
+    let mut result = Vec::new();
+    let mut stack = vec![gadget.clone()];
+    while let Some(top) = stack.pop() {
+        let children = top.children.get_vec().await.clone();
+        stack.extend(children.into_iter().rev());
+        result.push(top);
+    }
+    result
+}
+
 fn gadget_default_inner() -> Arc<GadgetInner> {
     Arc::new_cyclic(|weak| {
         let back_ref = GadgetRef::from_weak(weak.clone());
         GadgetInner {
+            type_name: State::new(back_ref.clone(), "Gadget"),
+            id: State::new(back_ref.clone(), None),
+            classes: StateVec::new(back_ref.clone()),
             pos: State::new_from(back_ref.clone(), (0.0, 0.0)),
             dim: State::new_from(back_ref.clone(), (0.0, 0.0)),
             enabled: State::new(back_ref.clone(), true),
+            visible: State::new(back_ref.clone(), true),
+            min_dim: State::new(back_ref.clone(), ScalarPair::zero()),
+            max_dim: State::new_from(back_ref.clone(), (Scalar::INFINITY, Scalar::INFINITY)),
+            margin: State::new(back_ref.clone(), Insets::default()),
+            padding: State::new(back_ref.clone(), Insets::default()),
+            measurer: State::new(back_ref.clone(), None),
+            measure_generation: State::new(back_ref.clone(), 0),
             parent: State::new(back_ref.clone(), GadgetParent::None),
             children: StateVec::new(back_ref.clone()),
+            attached_window: OptionalState::new_empty(back_ref.clone()),
             brush: State::new(back_ref.clone(), Brush::default()),
             font: State::new_any(
                 back_ref.clone(),
                 skia_font_default_cjk(12.0).unwrap()),
             batch: State::new(back_ref.clone(), Batch::default()),
+            damage: StateVec::new(back_ref.clone()),
+            render_transform: State::new(back_ref.clone(), RenderTransform::default()),
+            opacity: State::new(back_ref.clone(), 1.0),
+            tooltip: State::new(back_ref.clone(), None),
             propagate: State::new(back_ref.clone(), true),
             accept_focus: State::new(back_ref.clone(), false),
             lock_focus: State::new(back_ref.clone(), false),
@@ -166,7 +378,12 @@ fn gadget_default_inner() -> Arc<GadgetInner> {
             mouse_down: StateVec::new(back_ref.clone()),
             mouse_pos: OptionalState::new(back_ref.clone(), None),
             mouse_drag: StateVec::new(back_ref.clone()),
+            on_mouse: Event::new(),
+            on_key: Event::new(),
             key_down: StateVec::new(back_ref.clone()),
+            key_handler: State::new(back_ref.clone(), None),
+            scroll_handler: State::new(back_ref.clone(), None),
+            cursor: State::new(back_ref.clone(), None),
             accept_text: State::new(back_ref.clone(), false),
             pre_edit: State::new(back_ref.clone(), String::new()),
             pre_edit_pos: State::new(back_ref.clone(), 0),
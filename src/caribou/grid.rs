@@ -0,0 +1,312 @@
+use std::future::Future;
+use std::pin::Pin;
+use crate::caribou::gadget::Gadget;
+use crate::caribou::layout::{Alignment, Layout, layout_arrange_on, layout_place,
+                             layout_unwatch_child, layout_watch_child};
+use crate::caribou::math::{Region, Scalar, ScalarPair};
+use crate::caribou::measure::Constraints;
+use crate::caribou::state::{Arbitrary, State};
+
+/// Container placing its children into the cells of a table of rows and columns. Where a child
+/// goes is the [`GridCell`] attached to it with [`Grid::set_cell`].
+pub struct Grid;
+
+/// Size of a row or column.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GridLength {
+    Fixed(Scalar),
+    /// As large as the largest measured size of the children in it. Children spanning several
+    /// tracks are not taken into account.
+    Auto,
+    /// A share of what is left after fixed and auto tracks, proportional to the factor.
+    Star(Scalar),
+}
+
+impl Default for GridLength {
+    fn default() -> Self {
+        GridLength::Star(1.0)
+    }
+}
+
+/// Attached grid placement of a child.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GridCell {
+    pub row: usize,
+    pub column: usize,
+    pub row_span: usize,
+    pub column_span: usize,
+    /// Override the container's cell alignment for this child.
+    pub horizontal: Option<Alignment>,
+    pub vertical: Option<Alignment>,
+}
+
+impl Default for GridCell {
+    fn default() -> Self {
+        Self {
+            row: 0,
+            column: 0,
+            row_span: 1,
+            column_span: 1,
+            horizontal: None,
+            vertical: None,
+        }
+    }
+}
+
+impl GridCell {
+    pub fn new(row: usize, column: usize) -> Self {
+        Self { row, column, ..Default::default() }
+    }
+
+    pub fn span(self, row_span: usize, column_span: usize) -> Self {
+        Self { row_span, column_span, ..self }
+    }
+
+    pub fn align(self, horizontal: Alignment, vertical: Alignment) -> Self {
+        Self {
+            horizontal: Some(horizontal),
+            vertical: Some(vertical),
+            ..self
+        }
+    }
+}
+
+const GRID_CELL_KEY: &str = "grid.cell";
+
+impl Grid {
+    pub async fn create(rows: Vec<GridLength>, columns: Vec<GridLength>) -> Gadget {
+        let gadget = Layout::create().await;
+
+        // Fill specialized data
+        let data = GridData {
+            rows: State::new(gadget.refer(), rows),
+            columns: State::new(gadget.refer(), columns),
+            row_gap: State::new(gadget.refer(), 0.0),
+            column_gap: State::new(gadget.refer(), 0.0),
+            horizontal: State::new(gadget.refer(), Alignment::Stretch),
+            vertical: State::new(gadget.refer(), Alignment::Stretch),
+        };
+        gadget.data.set_any(data.clone()).await;
+
+        // Fill common properties
+        gadget.type_name.set("Grid").await;
+        gadget.set_measurer(|grid, _| Box::pin(async move {
+            grid_measure(grid).await
+        })).await;
+
+        // Listen re-layout
+        gadget.children.listen_add(
+            "grid_children_add",
+            |event| Box::pin(async move {
+                let grid = event.gadget.get().unwrap();
+                layout_watch_child(&grid, &event.new_value, grid_arrange_boxed).await;
+                grid.invalidate_measure().await;
+                grid_arrange(grid).await;
+            })).await;
+
+        gadget.children.listen_remove(
+            "grid_children_remove",
+            |event| Box::pin(async move {
+                let grid = event.gadget.get().unwrap();
+                layout_unwatch_child(&event.old_value).await;
+                grid.invalidate_measure().await;
+                grid_arrange(grid).await;
+            })).await;
+
+        layout_arrange_on(&gadget.dim, "grid_arrange", grid_arrange_boxed, false).await;
+        layout_arrange_on(&gadget.padding, "grid_arrange", grid_arrange_boxed, true).await;
+        layout_arrange_on(&data.rows, "grid_arrange", grid_arrange_boxed, true).await;
+        layout_arrange_on(&data.columns, "grid_arrange", grid_arrange_boxed, true).await;
+        layout_arrange_on(&data.row_gap, "grid_arrange", grid_arrange_boxed, true).await;
+        layout_arrange_on(&data.column_gap, "grid_arrange", grid_arrange_boxed, true).await;
+        layout_arrange_on(&data.horizontal, "grid_arrange", grid_arrange_boxed, false).await;
+        layout_arrange_on(&data.vertical, "grid_arrange", grid_arrange_boxed, false).await;
+
+        gadget
+    }
+
+    /// Attaches a cell to a child, re-arranging its container.
+    pub async fn set_cell(child: &Gadget, cell: GridCell) {
+        child.values.set(GRID_CELL_KEY.to_string(), Arbitrary::new(cell)).await;
+    }
+
+    pub async fn cell(child: &Gadget) -> GridCell {
+        child.values.get(&GRID_CELL_KEY.to_string()).await
+            .and_then(|cell| cell.get::<GridCell>().copied())
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Clone)]
+pub struct GridData {
+    /// Row definitions, a single star row if empty.
+    pub rows: State<Vec<GridLength>>,
+    /// Column definitions, a single star column if empty.
+    pub columns: State<Vec<GridLength>>,
+    pub row_gap: State<Scalar>,
+    pub column_gap: State<Scalar>,
+    /// Default alignment of children within their cells.
+    pub horizontal: State<Alignment>,
+    pub vertical: State<Alignment>,
+}
+
+pub async fn grid_data(grid: &Gadget) -> GridData {
+    let data = grid.data.get_cloned().await;
+    let data = data.get::<GridData>().await;
+    (*data).clone()
+}
+
+/// A child with its cell clamped to the defined tracks.
+struct GridEntry {
+    child: Gadget,
+    cell: GridCell,
+    preferred: ScalarPair,
+}
+
+struct GridTracks {
+    rows: Vec<GridLength>,
+    columns: Vec<GridLength>,
+    row_gap: Scalar,
+    column_gap: Scalar,
+    entries: Vec<GridEntry>,
+}
+
+async fn grid_tracks(grid: &Gadget, data: &GridData) -> GridTracks {
+    let mut rows = data.rows.get_cloned().await;
+    let mut columns = data.columns.get_cloned().await;
+    if rows.is_empty() {
+        rows.push(GridLength::default());
+    }
+    if columns.is_empty() {
+        columns.push(GridLength::default());
+    }
+
+    let children = grid.children.get_vec().await.clone();
+    let mut entries = Vec::with_capacity(children.len());
+    for child in children {
+        let mut cell = Grid::cell(&child).await;
+        cell.row = cell.row.min(rows.len() - 1);
+        cell.column = cell.column.min(columns.len() - 1);
+        cell.row_span = cell.row_span.clamp(1, rows.len() - cell.row);
+        cell.column_span = cell.column_span.clamp(1, columns.len() - cell.column);
+        let preferred = child.measure(Constraints::unbounded()).await;
+        entries.push(GridEntry { child, cell, preferred });
+    }
+    GridTracks {
+        rows,
+        columns,
+        row_gap: data.row_gap.get_cloned().await,
+        column_gap: data.column_gap.get_cloned().await,
+        entries,
+    }
+}
+
+impl GridTracks {
+    fn resolve(&self, dim: ScalarPair) -> (Vec<Scalar>, Vec<Scalar>) {
+        let rows = grid_resolve_tracks(
+            &self.rows, dim.y, self.row_gap,
+            self.entries.iter()
+                .filter(|entry| entry.cell.row_span == 1)
+                .map(|entry| (entry.cell.row, entry.preferred.y)));
+        let columns = grid_resolve_tracks(
+            &self.columns, dim.x, self.column_gap,
+            self.entries.iter()
+                .filter(|entry| entry.cell.column_span == 1)
+                .map(|entry| (entry.cell.column, entry.preferred.x)));
+        (rows, columns)
+    }
+}
+
+/// Fixed and auto tracks at their size, star tracks as large as their content.
+async fn grid_measure(grid: Gadget) -> ScalarPair {
+    let data = grid_data(&grid).await;
+    let mut tracks = grid_tracks(&grid, &data).await;
+    let as_auto = |track: &GridLength| match track {
+        GridLength::Star(_) => GridLength::Auto,
+        track => *track,
+    };
+    tracks.rows = tracks.rows.iter().map(as_auto).collect();
+    tracks.columns = tracks.columns.iter().map(as_auto).collect();
+    let (rows, columns) = tracks.resolve(ScalarPair::zero());
+    ScalarPair::new(
+        columns.iter().sum::<Scalar>() + tracks.column_gap * (columns.len() - 1) as Scalar,
+        rows.iter().sum::<Scalar>() + tracks.row_gap * (rows.len() - 1) as Scalar)
+        + grid.padding.get_cloned().await.size()
+}
+
+fn grid_arrange_boxed(grid: Gadget) -> Pin<Box<dyn Future<Output=()> + Send + Sync>> {
+    Box::pin(grid_arrange(grid))
+}
+
+async fn grid_arrange(grid: Gadget) {
+    let data = grid_data(&grid).await;
+    let horizontal = data.horizontal.get_cloned().await;
+    let vertical = data.vertical.get_cloned().await;
+    let content = grid.content_region().await;
+    let tracks = grid_tracks(&grid, &data).await;
+    let (row_sizes, column_sizes) = tracks.resolve(content.size);
+    let row_offsets = grid_track_offsets(&row_sizes, tracks.row_gap);
+    let column_offsets = grid_track_offsets(&column_sizes, tracks.column_gap);
+
+    for entry in tracks.entries.iter() {
+        let cell = entry.cell;
+        let last_row = cell.row + cell.row_span - 1;
+        let last_column = cell.column + cell.column_span - 1;
+        let origin = content.origin
+            + ScalarPair::new(column_offsets[cell.column], row_offsets[cell.row]);
+        let end = content.origin
+            + ScalarPair::new(column_offsets[last_column] + column_sizes[last_column],
+                              row_offsets[last_row] + row_sizes[last_row]);
+        let area = end - origin;
+        let (x, width) = cell.horizontal.unwrap_or(horizontal)
+            .place(entry.preferred.x.min(area.x), area.x);
+        let (y, height) = cell.vertical.unwrap_or(vertical)
+            .place(entry.preferred.y.min(area.y), area.y);
+        layout_place(&entry.child, Region::from_origin_size(
+            origin + ScalarPair::new(x, y), (width, height))).await;
+    }
+}
+
+/// Sizes of the tracks along one axis, given the preferred sizes of the children occupying a
+/// single track.
+fn grid_resolve_tracks(tracks: &[GridLength], available: Scalar, gap: Scalar,
+                       occupants: impl Iterator<Item=(usize, Scalar)>) -> Vec<Scalar> {
+    let mut auto = vec![0.0 as Scalar; tracks.len()];
+    for (index, size) in occupants {
+        auto[index] = auto[index].max(size);
+    }
+    let mut sizes: Vec<Scalar> = tracks.iter().enumerate()
+        .map(|(index, track)| match track {
+            GridLength::Fixed(size) => *size,
+            GridLength::Auto => auto[index],
+            GridLength::Star(_) => 0.0,
+        })
+        .collect();
+    let stars: Scalar = tracks.iter()
+        .map(|track| match track {
+            GridLength::Star(factor) => factor.max(0.0),
+            _ => 0.0,
+        })
+        .sum();
+    let gaps = gap * (tracks.len() - 1) as Scalar;
+    let left = (available - gaps - sizes.iter().sum::<Scalar>()).max(0.0);
+    if stars > 0.0 {
+        for (size, track) in sizes.iter_mut().zip(tracks.iter()) {
+            if let GridLength::Star(factor) = track {
+                *size = left * factor.max(0.0) / stars;
+            }
+        }
+    }
+    sizes
+}
+
+fn grid_track_offsets(sizes: &[Scalar], gap: Scalar) -> Vec<Scalar> {
+    let mut offset = 0.0;
+    sizes.iter()
+        .map(|size| {
+            let begin = offset;
+            offset += size + gap;
+            begin
+        })
+        .collect()
+}
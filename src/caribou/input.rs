@@ -1,18 +1,13 @@
 use std::fmt::Debug;
 use crate::bit_flags;
-use crate::caribou::math::ScalarPair;
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum Modifier {
-    Shift, Control, Alt, Meta,
-}
+use crate::caribou::math::{Scalar, ScalarPair};
 
 bit_flags! {
     pub enum Modifier2: u32 {
-        shift = 0b00000001,
-        control = 0b00000010,
-        alt = 0b00000100,
-        meta = 0b00001000,
+        SHIFT = 0b00000001,
+        CONTROL = 0b00000010,
+        ALT = 0b00000100,
+        META = 0b00001000,
     }
 }
 
@@ -149,6 +144,33 @@ pub enum MouseButton {
     Other(u16),
 }
 
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum CursorIcon {
+    Default,
+    Pointer,
+    Text,
+    Crosshair,
+    Move,
+    Wait,
+    Progress,
+    Help,
+    NotAllowed,
+    Grab,
+    Grabbing,
+    ResizeHorizontal,
+    ResizeVertical,
+    ResizeNwse,
+    ResizeNesw,
+    ResizeColumn,
+    ResizeRow,
+}
+
+impl Default for CursorIcon {
+    fn default() -> Self {
+        CursorIcon::Default
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub struct DragInfo {
     pub button: MouseButton,
@@ -176,7 +198,12 @@ pub enum MouseEvent {
 pub struct KeyEvent {
     pub key: Key,
     pub is_down: bool,
+    /// Whether this is an automatic repeat of a key held down, which leaves the pressed keys as
+    /// they are.
+    pub repeat: bool,
     pub modifiers: Modifier2,
+    /// Platform code of the physical key, regardless of the keyboard layout.
+    pub scancode: u32,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -196,4 +223,54 @@ pub enum FocusEvent {
 pub enum FocusResult {
     Accept,
     Reject
-}
\ No newline at end of file
+}
+
+/// Distance scrolled by one line of a mouse wheel, in logical pixels.
+pub const SCROLL_LINE: Scalar = 40.0;
+
+/// How far a wheel or touchpad scrolled, positive towards the end of the content: down for the
+/// vertical and right for the horizontal axis.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ScrollDelta {
+    /// Lines, as reported by wheels turning in notches.
+    Lines(ScalarPair),
+    /// Logical pixels, as reported by touchpads and smooth wheels.
+    Pixels(ScalarPair),
+}
+
+impl ScrollDelta {
+    /// The distance in logical pixels, lines counting [`SCROLL_LINE`] each.
+    pub fn pixels(self) -> ScalarPair {
+        match self {
+            ScrollDelta::Lines(lines) => lines.times(SCROLL_LINE),
+            ScrollDelta::Pixels(pixels) => pixels,
+        }
+    }
+}
+
+/// Where a scroll is within a touchpad gesture. Wheels always report `Moved`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum TouchPhase {
+    Started,
+    Moved,
+    Ended,
+    Cancelled,
+}
+
+/// Scrolling by a mouse wheel or a touchpad over a gadget.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ScrollEvent {
+    /// Where the pointer is, in the space of the gadget handling the event.
+    pub pos: ScalarPair,
+    pub delta: ScrollDelta,
+    pub phase: TouchPhase,
+    /// Modifiers held while scrolling, e.g. Control to zoom instead.
+    pub modifiers: Modifier2,
+}
+
+impl ScrollEvent {
+    /// How far the content should move in logical pixels.
+    pub fn pixels(&self) -> ScalarPair {
+        self.delta.pixels()
+    }
+}
@@ -1,11 +1,74 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
 use log::info;
-use crate::caribou::batch::{begin_paint, Transform};
-use crate::caribou::gadget::{Gadget, GadgetParent, GadgetRef};
-use crate::caribou::math::{Region};
-use crate::caribou::state::{Listener, State};
+use tokio::sync::Mutex;
+use crate::caribou::async_runtime;
+use crate::caribou::batch::{Batch, begin_paint, BatchOp, Transform};
+use crate::caribou::gadget::{Gadget, gadget_button_push, gadget_button_remove, gadget_pointer_put,
+                             gadget_pointer_take, gadget_update_attachment, GadgetParent, GadgetRef};
+use crate::caribou::math::{Region, Scalar, ScalarPair};
+use crate::caribou::state::{Arbitrary, Listener, State, StateChangedEvent};
 
 pub struct Layout;
 
+/// Main axis of containers arranging their children in a line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Orientation {
+    Vertical,
+    Horizontal,
+}
+
+/// Placement of a child within the space given to it along one axis.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Alignment {
+    #[default]
+    Start,
+    Center,
+    End,
+    /// Fill the space, changing the child's dimension.
+    Stretch,
+}
+
+impl Orientation {
+    /// The component of `pair` along this axis.
+    pub fn main(&self, pair: ScalarPair) -> Scalar {
+        match self {
+            Orientation::Vertical => pair.y,
+            Orientation::Horizontal => pair.x,
+        }
+    }
+
+    /// The component of `pair` across this axis.
+    pub fn cross(&self, pair: ScalarPair) -> Scalar {
+        match self {
+            Orientation::Vertical => pair.x,
+            Orientation::Horizontal => pair.y,
+        }
+    }
+
+    /// Builds a pair from its main and cross components.
+    pub fn pair(&self, main: Scalar, cross: Scalar) -> ScalarPair {
+        match self {
+            Orientation::Vertical => ScalarPair::new(cross, main),
+            Orientation::Horizontal => ScalarPair::new(main, cross),
+        }
+    }
+}
+
+impl Alignment {
+    /// Offset and extent of a child of extent `size` placed within `available`.
+    pub fn place(&self, size: Scalar, available: Scalar) -> (Scalar, Scalar) {
+        match self {
+            Alignment::Start => (0.0, size),
+            Alignment::Center => ((available - size) / 2.0, size),
+            Alignment::End => (available - size, size),
+            Alignment::Stretch => (0.0, available),
+        }
+    }
+}
+
 impl Layout {
     pub async fn create() -> Gadget {
         let gadget = Gadget::default();
@@ -21,6 +84,18 @@ impl Layout {
                 new_child.pos.listen("layout_child_pos",
                                      layout_child_listen(event.gadget.clone()))
                     .await;
+                new_child.render_transform.listen("layout_child_render_transform",
+                                                  layout_child_listen(event.gadget.clone()))
+                    .await;
+                new_child.opacity.listen("layout_child_opacity",
+                                         layout_child_listen(event.gadget.clone()))
+                    .await;
+                new_child.dim.listen("layout_child_dim",
+                                     layout_child_listen(event.gadget.clone()))
+                    .await;
+                new_child.visible.listen("layout_child_visible",
+                                         layout_child_listen(event.gadget.clone()))
+                    .await;
                 layout_update_batch(event.gadget.get().unwrap()).await;
             }) }).await;
 
@@ -30,63 +105,66 @@ impl Layout {
                 let old_child = event.old_value;
                 old_child.batch.remove_listener("layout_child_batch").await;
                 old_child.pos.remove_listener("layout_child_pos").await;
+                old_child.render_transform.remove_listener("layout_child_render_transform").await;
+                old_child.opacity.remove_listener("layout_child_opacity").await;
+                old_child.dim.remove_listener("layout_child_dim").await;
+                old_child.visible.remove_listener("layout_child_visible").await;
+                let layout = event.gadget.get().unwrap();
+                if let Some(data) = layout_data(&layout).await {
+                    let _routing = data.routing.lock().await;
+                    let hovered = {
+                        let mut hovering = data.hovering.get_mut().await;
+                        if data.capture.get_cloned().await.as_ref() == Some(&old_child) {
+                            data.capture.set(None).await;
+                        }
+                        if hovering.as_ref() == Some(&old_child) {
+                            hovering.take()
+                        } else {
+                            None
+                        }
+                    };
+                    if let Some(old_child) = hovered {
+                        gadget_pointer_take(&old_child).await;
+                    }
+                }
+                layout_update_batch(layout).await;
+            }) }).await;
+
+        gadget.dim.listen(
+            "layout_dim",
+            |event| { Box::pin(async move {
                 layout_update_batch(event.gadget.get().unwrap()).await;
             }) }).await;
 
         gadget.mouse_pos.listen_set(
             "layout_mouse_pos_set",
             |event| { Box::pin(async move {
-                let gadget = event.gadget.get().unwrap();
-                let pos = event.value;
-                let children = gadget.children.get_vec().await;
-                for child in children.iter() {
-                    let child_pos = child.pos.get_cloned().await;
-                    let child_dim = child.dim.get_cloned().await;
-                    let region = Region::from_origin_size(child_pos, child_dim);
-                    if region.contains(pos) {
-                        child.mouse_pos.put(pos - child_pos).await;
-                    }
-                }
+                layout_route_pointer(&event.gadget.get().unwrap()).await;
             }) }).await;
 
         gadget.mouse_pos.listen_change(
             "layout_mouse_pos_change",
             |event| { Box::pin(async move {
-                let gadget = event.gadget.get().unwrap();
-                let pos = event.new_value;
-                let children = gadget.children.get_vec().await;
-                for child in children.iter() {
-                    let child_pos = child.pos.get_cloned().await;
-                    let child_dim = child.dim.get_cloned().await;
-                    let region = Region::from_origin_size(child_pos, child_dim);
-                    if region.contains(pos) {
-                        child.mouse_pos.put(pos - child_pos).await;
-                    } else {
-                        child.mouse_down.clear().await;
-                        child.mouse_pos.take().await;
-                    }
-                }
+                layout_route_pointer(&event.gadget.get().unwrap()).await;
             }) }).await;
 
         gadget.mouse_pos.listen_unset(
             "layout_mouse_pos_unset",
             |event| { Box::pin(async move {
-                let gadget = event.gadget.get().unwrap();
-                let children = gadget.children.get_vec().await;
-                for child in children.iter() {
-                    child.mouse_down.clear().await;
-                    child.mouse_pos.take().await;
-                }
+                layout_route_pointer(&event.gadget.get().unwrap()).await;
             }) }).await;
 
         gadget.mouse_down.listen_add(
             "layout_mouse_down_add",
             |event| { Box::pin(async move {
-                let gadget = event.gadget.get().unwrap();
-                let children = gadget.children.get_vec().await;
-                for child in children.iter() {
+                let layout = event.gadget.get().unwrap();
+                if let Some(child) = layout_hovering(&layout).await {
                     if child.mouse_pos.is_set().await {
-                        child.mouse_down.push(event.new_value).await;
+                        // Pressing captures the pointer until every button is released
+                        if let Some(data) = layout_data(&layout).await {
+                            data.capture.set(Some(child.clone())).await;
+                        }
+                        gadget_button_push(&child, event.new_value).await;
                     }
                 }
             }) }).await;
@@ -94,20 +172,28 @@ impl Layout {
         gadget.mouse_down.listen_remove(
             "layout_mouse_down_remove",
             |event| { Box::pin(async move {
-                let gadget = event.gadget.get().unwrap();
-                let children = gadget.children.get_vec().await;
-                for child in children.iter() {
+                let layout = event.gadget.get().unwrap();
+                if let Some(child) = layout_hovering(&layout).await {
                     if child.mouse_pos.is_set().await {
-                        child.mouse_down.remove(&event.old_value).await;
+                        gadget_button_remove(&child, event.old_value).await;
                     }
                 }
+                if layout.mouse_down.get_vec().await.is_empty() {
+                    layout_release_capture(&layout).await;
+                }
             }) }).await;
 
-        // Fill specialized data
+        gadget.type_name.set("Layout").await;
+
+        // Composition bookkeeping goes into values, leaving the specialized data to containers
+        // built on top of this one
         let data = LayoutData {
+            routing: Arc::new(Mutex::new(())),
             hovering: State::new(gadget.refer(), None),
+            capture: State::new(gadget.refer(), None),
+            child_regions: State::new(gadget.refer(), Vec::new()),
         };
-        gadget.data.set_any(data).await;
+        gadget.values.set(LAYOUT_DATA_KEY.to_string(), Arbitrary::new(data)).await;
 
         // Fill common properties
         gadget.dim.set_from((150.0, 150.0)).await;
@@ -118,42 +204,506 @@ impl Layout {
 
     pub async fn add_child(parent: &Gadget, child: Gadget) {
         child.parent.set(GadgetParent::Gadget(parent.refer())).await;
-        parent.children.push(child).await;
+        parent.children.push(child.clone()).await;
+        gadget_update_attachment(&child, parent.attached_window.get().await).await;
     }
 
     pub async fn remove_child(parent: &Gadget, child: Gadget) {
         child.parent.set(GadgetParent::None).await;
-        parent.children.get_vec_mut().await.retain(|c| c != &child);
+        parent.children.remove(&child).await;
+        gadget_update_attachment(&child, None).await;
     }
 }
 
+#[derive(Clone)]
 pub struct LayoutData {
+    /// Held from choosing the child under the pointer until it has been given the pointer, so
+    /// that routing for one position cannot overtake routing for the one before. The listeners
+    /// of the children run under it and must not route the pointer of this layout in place.
+    routing: Arc<Mutex<()>>,
+    /// Topmost child under the pointer, the only one given it. Its lock is never held while the
+    /// pointer is given or taken.
     hovering: State<Option<Gadget>>,
+    /// Child keeping the pointer even outside its bounds, from a press until every button is
+    /// released or it releases the capture.
+    capture: State<Option<Gadget>>,
+    /// Bounds of each child in the layout's space as of the last composition, in child order.
+    /// Its lock also serializes updates of the layout batch.
+    child_regions: State<Vec<Region>>,
+}
+
+const LAYOUT_DATA_KEY: &str = "layout";
+
+async fn layout_data(layout: &Gadget) -> Option<LayoutData> {
+    layout.values.get(&LAYOUT_DATA_KEY.to_string()).await?
+        .get::<LayoutData>()
+        .cloned()
+}
+
+/// Child the pointer is given to, if any.
+pub async fn layout_hovering(layout: &Gadget) -> Option<Gadget> {
+    layout_data(layout).await?.hovering.get_cloned().await
+}
+
+/// Gives the pointer to the topmost child under it, taking it from the one that had it if
+/// another. Its position is read under the routing lock, so of several routings waiting for it
+/// the last one leaves the pointer where it is now rather than where an earlier event had it.
+async fn layout_route_pointer(layout: &Gadget) {
+    let data = match layout_data(layout).await {
+        None => return,
+        Some(data) => data,
+    };
+    let _routing = data.routing.lock().await;
+    let pos = layout.mouse_pos.get().await;
+    let (previous, target) = {
+        let mut hovering = data.hovering.get_mut().await;
+        let mut target = None;
+        if let Some(pos) = pos {
+            match data.capture.get_cloned().await {
+                Some(captured) => {
+                    if let Some(local) = layout_child_transform(&captured).await.unmap_point(pos) {
+                        target = Some((captured, local));
+                    }
+                }
+                None => {
+                    let children = layout.children.get_vec().await.clone();
+                    for child in children.iter().rev() {
+                        if let Some(local) = layout_child_local_pos(child, pos).await {
+                            target = Some((child.clone(), local));
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+        let hovered = target.as_ref().map(|(child, _)| child.clone());
+        let previous = if *hovering != hovered {
+            std::mem::replace(&mut *hovering, hovered)
+        } else {
+            None
+        };
+        (previous, target)
+    };
+    // Without the hovering lock, as the listeners of the children may capture or release them
+    if let Some(previous) = previous {
+        gadget_pointer_take(&previous).await;
+    }
+    if let Some((child, local)) = target {
+        gadget_pointer_put(&child, local).await;
+    }
+}
+
+/// Lets the pointer go to whatever child is under it again, if a child had captured it. The
+/// pointer is routed again afterwards, as this may be called while it is being routed.
+async fn layout_release_capture(layout: &Gadget) {
+    let data = match layout_data(layout).await {
+        None => return,
+        Some(data) => data,
+    };
+    let released = {
+        let _hovering = data.hovering.get_mut().await;
+        let mut capture = data.capture.get_mut().await;
+        capture.take().is_some()
+    };
+    if released {
+        let layout = layout.clone();
+        async_runtime().spawn(async move {
+            layout_route_pointer(&layout).await;
+        });
+    }
+}
+
+impl Gadget {
+    /// Keeps giving the pointer to this gadget, even outside its bounds or under other
+    /// gadgets, until every button is released or [`Gadget::release_capture`] is called.
+    /// Pressing a button over a gadget in a [`Layout`] already captures it.
+    pub async fn capture_pointer(&self) {
+        let mut child = self.clone();
+        while let GadgetParent::Gadget(parent) = child.parent.get_cloned().await {
+            let layout = match parent.get() {
+                None => return,
+                Some(layout) => layout,
+            };
+            if let Some(data) = layout_data(&layout).await {
+                let _hovering = data.hovering.get_mut().await;
+                data.capture.set(Some(child.clone())).await;
+            }
+            child = layout;
+        }
+    }
+
+    /// Gives the pointer back to whatever is under it, if this gadget or one of its ancestors
+    /// had captured it.
+    pub async fn release_capture(&self) {
+        let mut child = self.clone();
+        while let GadgetParent::Gadget(parent) = child.parent.get_cloned().await {
+            let layout = match parent.get() {
+                None => return,
+                Some(layout) => layout,
+            };
+            if let Some(data) = layout_data(&layout).await {
+                let captured = data.capture.get_cloned().await;
+                if captured.as_ref() == Some(&child) {
+                    layout_release_capture(&layout).await;
+                }
+            }
+            child = layout;
+        }
+    }
+}
+
+/// How a child is composed into its layout: its transform, its bounds in the layout's space and
+/// its batch. Invisible children get an empty batch and bounds, keeping their index.
+async fn layout_child_composition(child: &Gadget) -> (Transform, Region, Batch) {
+    let transform = layout_child_transform(child).await;
+    if !child.visible.get_cloned().await {
+        return (transform, Region::default(), Batch::default());
+    }
+    let dim = child.dim.get_cloned().await;
+    let bounds = transform.map_region(Region::from_origin_size(ScalarPair::zero(), dim));
+    (transform, bounds, child.batch.get_cloned().await)
 }
 
+/// Composes every child again and damages the whole layout.
 async fn layout_update_batch(layout: Gadget) {
-    let children = layout.children.get_vec().await;
+    let data = match layout_data(&layout).await {
+        None => return,
+        Some(data) => data,
+    };
+    let mut regions = data.child_regions.get_mut().await;
+    let children = layout.children.get_vec().await.clone();
     let mut artist = begin_paint();
+    regions.clear();
     for child in children.iter() {
-        artist = artist.batch(
-            child.pos.get().await.into_translate(),
-            child.batch.get_cloned().await
-        );
+        let (transform, bounds, batch) = layout_child_composition(child).await;
+        child.take_damage().await;
+        regions.push(bounds);
+        artist = artist.batch(transform, batch);
     }
-    let batch = artist.finish();
+    let dim = layout.dim.get_cloned().await;
     let batch = begin_paint()
-        .batch(Transform::from_clip(layout.dim.get_cloned().await),
-               batch)
+        .batch(Transform::from_clip(dim), artist.finish())
         .finish();
     // info!("Layout batch: {:?}", batch);
+    layout.report_damage(Region::from_origin_size(ScalarPair::zero(), dim)).await;
+    layout.batch.set(batch).await;
+}
+
+/// Composes a single child again, damaging only what it reported, or its old and new bounds
+/// if it reported nothing or moved.
+async fn layout_update_child(layout: Gadget, child: Gadget) {
+    let data = match layout_data(&layout).await {
+        None => return,
+        Some(data) => data,
+    };
+    let mut regions = data.child_regions.get_mut().await;
+    let index = match layout.children.get_vec().await.iter().position(|c| c == &child) {
+        None => return,
+        Some(index) => index,
+    };
+    let mut batch = layout.batch.get_cloned().await;
+    let composed = match batch.first_mut() {
+        Some(BatchOp::Batch { batch, .. }) if index < regions.len() && index < batch.len() =>
+            batch,
+        _ => {
+            // Not composed with this child yet
+            drop(regions);
+            layout_update_batch(layout).await;
+            return;
+        }
+    };
+    let (transform, bounds, child_batch) = layout_child_composition(&child).await;
+    composed[index] = BatchOp::Batch { transform, batch: child_batch };
+    let old_bounds = std::mem::replace(&mut regions[index], bounds);
+    let reported = child.take_damage().await;
+    let damage = if bounds.is_empty() {
+        // Hidden or still hidden, damaging what was shown before if anything
+        vec![old_bounds]
+    } else if old_bounds.is_empty() {
+        vec![bounds]
+    } else if reported.is_empty() || old_bounds != bounds {
+        vec![old_bounds.union(bounds)]
+    } else {
+        reported.into_iter().map(|region| transform.map_region(region)).collect()
+    };
+    let clip = Region::from_origin_size(ScalarPair::zero(), layout.dim.get_cloned().await);
+    for region in damage.into_iter().filter(|region| !region.is_empty()) {
+        if let Some(region) = region.intersection(clip) {
+            layout.report_damage(region).await;
+        }
+    }
     layout.batch.set(batch).await;
 }
 
-fn layout_child_listen<E: Send + Sync>(layout: GadgetRef) -> Listener<E> {
+const LAYOUT_PREFERRED_DIM_KEY: &str = "layout.preferred_dim";
+const LAYOUT_ASSIGNED_DIM_KEY: &str = "layout.assigned_dim";
+
+async fn layout_attached_dim(child: &Gadget, key: &str) -> Option<ScalarPair> {
+    layout_attached_dim_in(&*child.values.get_map().await, key)
+}
+
+fn layout_attached_dim_in(values: &HashMap<String, Arbitrary>, key: &str) -> Option<ScalarPair> {
+    values.get(key)?.get::<ScalarPair>().copied()
+}
+
+/// The dimension a child asks its container for when it has no measurer. Unless set with
+/// [`layout_set_preferred_dim`], this is the dimension it had before a container first assigned
+/// one, or the one it was last given from outside the container.
+pub async fn layout_preferred_dim(child: &Gadget) -> ScalarPair {
+    match layout_attached_dim(child, LAYOUT_PREFERRED_DIM_KEY).await {
+        Some(dim) => dim,
+        None => child.dim.get_cloned().await,
+    }
+}
+
+/// Sets the dimension a child asks for, making arranging containers lay out again.
+pub async fn layout_set_preferred_dim<S: Into<ScalarPair>>(child: &Gadget, dim: S) {
+    child.values.get_map_mut().await
+        .insert(LAYOUT_PREFERRED_DIM_KEY.to_string(), Arbitrary::new(dim.into()));
+    child.invalidate_measure().await;
+}
+
+/// Gives a child the dimension chosen by its container, kept within the child's `min_dim` and
+/// `max_dim`, remembering what it asked for first.
+///
+/// The values lock is held while the dimension is replaced, so [`layout_child_dim_changed`] sees
+/// the assigned dimension and the one it was recorded as together. A dimension set from outside
+/// that this overwrites before its listener ran becomes the preferred one here.
+pub async fn layout_assign_dim(child: &Gadget, dim: ScalarPair) {
+    let dim = child.own_constraints().await.constrain(dim);
+    let mut values = child.values.get_map_mut().await;
+    let mut current = child.dim.get_mut().await;
+    let old = *current;
+    // Silently, these are bookkeeping rather than something to react to
+    let outside = match layout_attached_dim_in(&values, LAYOUT_ASSIGNED_DIM_KEY) {
+        Some(assigned) => assigned != old,
+        None => {
+            values.entry(LAYOUT_PREFERRED_DIM_KEY.to_string())
+                .or_insert_with(|| Arbitrary::new(old));
+            false
+        }
+    };
+    if outside {
+        values.insert(LAYOUT_PREFERRED_DIM_KEY.to_string(), Arbitrary::new(old));
+    }
+    values.insert(LAYOUT_ASSIGNED_DIM_KEY.to_string(), Arbitrary::new(dim));
+    *current = dim;
+    drop(current);
+    drop(values);
+    if old != dim {
+        child.dim.notify(old).await;
+    }
+    if outside {
+        child.invalidate_measure().await;
+    }
+}
+
+/// Places a child into the slot its container gave it, keeping its margin free around it.
+/// `slot` is the outer size the child measured, margin included.
+pub async fn layout_place(child: &Gadget, slot: Region) {
+    let region = child.margin.get_cloned().await.shrink(slot);
+    child.pos.set_if_changed(region.origin).await;
+    layout_assign_dim(child, region.size).await;
+}
+
+/// To be called by containers when a child's dimension changed. A dimension other than the one
+/// assigned last was set from outside and becomes the preferred one, in which case `true` is
+/// returned. It then counts as assigned, so [`layout_assign_dim`] does not take it up again.
+pub async fn layout_child_dim_changed(child: &Gadget) -> bool {
+    let mut values = child.values.get_map_mut().await;
+    let dim = child.dim.get_cloned().await;
+    match layout_attached_dim_in(&values, LAYOUT_ASSIGNED_DIM_KEY) {
+        Some(assigned) if assigned == dim => false,
+        Some(_) => {
+            values.insert(LAYOUT_PREFERRED_DIM_KEY.to_string(), Arbitrary::new(dim));
+            values.insert(LAYOUT_ASSIGNED_DIM_KEY.to_string(), Arbitrary::new(dim));
+            true
+        }
+        None => true,
+    }
+}
+
+/// Lays out all children of an arranging container again.
+pub type Arranger = fn(Gadget) -> Pin<Box<dyn Future<Output=()> + Send + Sync>>;
+
+/// Makes an arranging container lay out again whenever one of its states changes. States
+/// affecting its own measure `invalidate` it first, telling its container as well.
+pub async fn layout_arrange_on<T>(state: &State<T>, name: &'static str, arrange: Arranger,
+                                  invalidate: bool)
+where
+    T: Send + Sync + Clone + 'static,
+{
+    state.listen(name, move |event| Box::pin(async move {
+        let container = match event.gadget.get() {
+            None => return,
+            Some(container) => container,
+        };
+        if invalidate {
+            container.invalidate_measure().await;
+        }
+        arrange(container).await;
+    })).await;
+}
+
+/// Makes an arranging container lay out again, and tell its own container, whenever something
+/// affecting the measure of `child` changes: its dimension set from outside, its bounds or
+/// margin, its measurer or its attached values.
+pub async fn layout_watch_child(container: &Gadget, child: &Gadget, arrange: Arranger) {
+    let cr = container.refer();
+    child.dim.listen(
+        "layout_watch_dim",
+        move |event| {
+            let cr = cr.clone();
+            Box::pin(async move {
+                let (container, child) = match (cr.get(), event.gadget.get()) {
+                    (Some(container), Some(child)) => (container, child),
+                    _ => return,
+                };
+                if layout_child_dim_changed(&child).await {
+                    container.invalidate_measure().await;
+                    arrange(container).await;
+                }
+            })
+        }).await;
+
+    let cr = container.refer();
+    child.values.listen(
+        "layout_watch_values",
+        move |_| {
+            let cr = cr.clone();
+            Box::pin(async move {
+                if let Some(container) = cr.get() {
+                    container.invalidate_measure().await;
+                    arrange(container).await;
+                }
+            })
+        }).await;
+
+    for bound in [&child.min_dim, &child.max_dim] {
+        let cr = container.refer();
+        bound.listen(
+            "layout_watch_bounds",
+            move |_| {
+                let cr = cr.clone();
+                Box::pin(async move {
+                    if let Some(container) = cr.get() {
+                        container.invalidate_measure().await;
+                        arrange(container).await;
+                    }
+                })
+            }).await;
+    }
+
+    let cr = container.refer();
+    child.margin.listen(
+        "layout_watch_bounds",
+        move |_| {
+            let cr = cr.clone();
+            Box::pin(async move {
+                if let Some(container) = cr.get() {
+                    container.invalidate_measure().await;
+                    arrange(container).await;
+                }
+            })
+        }).await;
+
+    let cr = container.refer();
+    child.measure_generation.listen(
+        "layout_watch_measure",
+        move |_| {
+            let cr = cr.clone();
+            Box::pin(async move {
+                if let Some(container) = cr.get() {
+                    container.invalidate_measure().await;
+                    arrange(container).await;
+                }
+            })
+        }).await;
+}
+
+pub async fn layout_unwatch_child(child: &Gadget) {
+    child.dim.remove_listener("layout_watch_dim").await;
+    child.values.remove_listener("layout_watch_values").await;
+    child.min_dim.remove_listener("layout_watch_bounds").await;
+    child.max_dim.remove_listener("layout_watch_bounds").await;
+    child.margin.remove_listener("layout_watch_bounds").await;
+    child.measure_generation.remove_listener("layout_watch_measure").await;
+}
+
+/// Transform placing a child in its parent, composed from its position, render transform and
+/// opacity.
+pub async fn layout_child_transform(child: &Gadget) -> Transform {
+    let pos = child.pos.get_cloned().await;
+    let dim = child.dim.get_cloned().await;
+    let opacity = child.opacity.get_cloned().await;
+    child.render_transform.get().await
+        .compose(pos, dim)
+        .opacity(opacity)
+}
+
+/// Maps a point in the parent's space into the child's space, `None` if it falls outside the
+/// child or the child is invisible.
+pub async fn layout_child_local_pos(child: &Gadget, pos: ScalarPair) -> Option<ScalarPair> {
+    if !child.visible.get_cloned().await {
+        return None;
+    }
+    let local = layout_child_transform(child).await.unmap_point(pos)?;
+    let dim = child.dim.get_cloned().await;
+    if Region::from_origin_size(ScalarPair::zero(), dim).contains(local) {
+        Some(local)
+    } else {
+        None
+    }
+}
+
+fn layout_child_listen<T: Send + Sync>(layout: GadgetRef) -> Listener<StateChangedEvent<T>> {
     Box::new(move |event| {
         let layout = layout.clone();
         Box::pin(async move {
-            layout_update_batch(layout.get().unwrap()).await;
+            let child = match event.gadget.get() {
+                None => return,
+                Some(child) => child,
+            };
+            layout_update_child(layout.get().unwrap(), child).await;
         })
     })
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::caribou::{async_runtime, caribou_test_init};
+    use super::*;
+
+    #[test]
+    fn assigned_dims_are_not_preferred() {
+        caribou_test_init();
+        async_runtime().block_on(async {
+            let child = Gadget::default();
+            child.dim.set(ScalarPair::new(10.0, 10.0)).await;
+            layout_assign_dim(&child, ScalarPair::new(20.0, 20.0)).await;
+            layout_assign_dim(&child, ScalarPair::new(30.0, 30.0)).await;
+            // Listeners of both assignments see the last one
+            assert!(!layout_child_dim_changed(&child).await);
+            assert!(!layout_child_dim_changed(&child).await);
+            assert_eq!(layout_preferred_dim(&child).await, ScalarPair::new(10.0, 10.0));
+        });
+    }
+
+    #[test]
+    fn dims_set_from_outside_are_preferred() {
+        caribou_test_init();
+        async_runtime().block_on(async {
+            let child = Gadget::default();
+            layout_assign_dim(&child, ScalarPair::new(20.0, 20.0)).await;
+            child.dim.set(ScalarPair::new(40.0, 40.0)).await;
+            assert!(layout_child_dim_changed(&child).await);
+            assert_eq!(layout_preferred_dim(&child).await, ScalarPair::new(40.0, 40.0));
+
+            // Overwritten by the container before its listener ran
+            child.dim.set(ScalarPair::new(50.0, 50.0)).await;
+            layout_assign_dim(&child, ScalarPair::new(20.0, 20.0)).await;
+            assert!(!layout_child_dim_changed(&child).await);
+            assert_eq!(layout_preferred_dim(&child).await, ScalarPair::new(50.0, 50.0));
+        });
+    }
+}
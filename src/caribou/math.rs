@@ -191,10 +191,123 @@ impl Region {
     pub fn center(&self) -> ScalarPair {
         self.origin + self.size.times(0.5)
     }
+
+    pub fn end(&self) -> ScalarPair {
+        self.origin + self.size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.size.x <= 0.0 || self.size.y <= 0.0
+    }
+
+    /// Smallest region covering both.
+    pub fn union<T: Into<Region>>(&self, other: T) -> Region {
+        let other: Region = other.into();
+        let begin = ScalarPair::new(self.origin.x.min(other.origin.x),
+                                    self.origin.y.min(other.origin.y));
+        let end = ScalarPair::new(self.end().x.max(other.end().x),
+                                  self.end().y.max(other.end().y));
+        Region::from_begin_end(begin, end)
+    }
+
+    /// Overlapping part of both, `None` if they do not overlap.
+    pub fn intersection<T: Into<Region>>(&self, other: T) -> Option<Region> {
+        let other: Region = other.into();
+        let begin = ScalarPair::new(self.origin.x.max(other.origin.x),
+                                    self.origin.y.max(other.origin.y));
+        let end = ScalarPair::new(self.end().x.min(other.end().x),
+                                  self.end().y.min(other.end().y));
+        let region = Region::from_begin_end(begin, end);
+        if region.is_empty() { None } else { Some(region) }
+    }
 }
 
 impl From<(ScalarPair, ScalarPair)> for Region {
     fn from((origin, size): (ScalarPair, ScalarPair)) -> Self {
         Self { origin, size }
     }
-}
\ No newline at end of file
+}
+
+/// Space around the four edges of something, e.g. the padding of a container.
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub struct Insets {
+    pub left: Scalar,
+    pub top: Scalar,
+    pub right: Scalar,
+    pub bottom: Scalar,
+}
+
+impl Insets {
+    pub fn new(left: Scalar, top: Scalar, right: Scalar, bottom: Scalar) -> Self {
+        Self { left, top, right, bottom }
+    }
+
+    pub fn uniform(inset: Scalar) -> Self {
+        Self::new(inset, inset, inset, inset)
+    }
+
+    pub fn symmetric(horizontal: Scalar, vertical: Scalar) -> Self {
+        Self::new(horizontal, vertical, horizontal, vertical)
+    }
+
+    /// Offset of the inner area from the outer origin.
+    pub fn begin(&self) -> ScalarPair {
+        ScalarPair::new(self.left, self.top)
+    }
+
+    /// Total space taken on both axes.
+    pub fn size(&self) -> ScalarPair {
+        ScalarPair::new(self.left + self.right, self.top + self.bottom)
+    }
+
+    /// The region inside these insets, never smaller than nothing.
+    pub fn shrink<T: Into<Region>>(&self, region: T) -> Region {
+        let region: Region = region.into();
+        let size = region.size - self.size();
+        Region::from_origin_size(region.origin + self.begin(),
+                                 (size.x.max(0.0), size.y.max(0.0)))
+    }
+
+    /// The region these insets surround.
+    pub fn grow<T: Into<Region>>(&self, region: T) -> Region {
+        let region: Region = region.into();
+        Region::from_origin_size(region.origin - self.begin(), region.size + self.size())
+    }
+}
+
+impl From<Scalar> for Insets {
+    fn from(inset: Scalar) -> Self {
+        Self::uniform(inset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn region(x: Scalar, y: Scalar, w: Scalar, h: Scalar) -> Region {
+        Region::from_origin_size((x, y), (w, h))
+    }
+
+    #[test]
+    fn union_covers_both() {
+        assert_eq!(region(0.0, 0.0, 10.0, 10.0).union(region(20.0, 5.0, 5.0, 10.0)),
+                   region(0.0, 0.0, 25.0, 15.0));
+        assert_eq!(region(0.0, 0.0, 10.0, 10.0).union(region(2.0, 2.0, 3.0, 3.0)),
+                   region(0.0, 0.0, 10.0, 10.0));
+    }
+
+    #[test]
+    fn intersection_of_overlapping() {
+        assert_eq!(region(0.0, 0.0, 10.0, 10.0).intersection(region(5.0, 5.0, 10.0, 10.0)),
+                   Some(region(5.0, 5.0, 5.0, 5.0)));
+        assert_eq!(region(0.0, 0.0, 10.0, 10.0).intersection(region(2.0, 2.0, 3.0, 3.0)),
+                   Some(region(2.0, 2.0, 3.0, 3.0)));
+    }
+
+    #[test]
+    fn intersection_of_disjoint_or_touching_is_none() {
+        assert_eq!(region(0.0, 0.0, 10.0, 10.0).intersection(region(20.0, 0.0, 5.0, 5.0)), None);
+        assert_eq!(region(0.0, 0.0, 10.0, 10.0).intersection(region(10.0, 0.0, 5.0, 5.0)), None);
+    }
+}
@@ -0,0 +1,139 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use crate::caribou::gadget::Gadget;
+use crate::caribou::layout::layout_preferred_dim;
+use crate::caribou::math::{Scalar, ScalarPair};
+
+/// Bounds a parent puts on the size of a child during measuring.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Constraints {
+    pub min: ScalarPair,
+    pub max: ScalarPair,
+}
+
+impl Constraints {
+    pub fn new<S1: Into<ScalarPair>, S2: Into<ScalarPair>>(min: S1, max: S2) -> Self {
+        Self {
+            min: min.into(),
+            max: max.into(),
+        }
+    }
+
+    /// Exactly this size.
+    pub fn tight<S: Into<ScalarPair>>(size: S) -> Self {
+        let size = size.into();
+        Self::new(size, size)
+    }
+
+    /// Anything up to this size.
+    pub fn loose<S: Into<ScalarPair>>(max: S) -> Self {
+        Self::new(ScalarPair::zero(), max)
+    }
+
+    pub fn unbounded() -> Self {
+        Self::loose((Scalar::INFINITY, Scalar::INFINITY))
+    }
+
+    /// Clamps a size into the bounds, the minimum winning over the maximum.
+    pub fn constrain<S: Into<ScalarPair>>(&self, size: S) -> ScalarPair {
+        let size = size.into();
+        ScalarPair::new(size.x.min(self.max.x).max(self.min.x),
+                        size.y.min(self.max.y).max(self.min.y))
+    }
+
+    /// Narrows these bounds by others, as far as they do not contradict.
+    pub fn restrict(&self, other: Constraints) -> Constraints {
+        let min = self.constrain(other.min);
+        let max = self.constrain(other.max);
+        Constraints::new(min, ScalarPair::new(max.x.max(min.x), max.y.max(min.y)))
+    }
+
+    /// The bounds left for content after taking `amount` away, e.g. padding.
+    pub fn shrink<S: Into<ScalarPair>>(&self, amount: S) -> Constraints {
+        let amount = amount.into();
+        let min = ScalarPair::new((self.min.x - amount.x).max(0.0),
+                                  (self.min.y - amount.y).max(0.0));
+        let max = ScalarPair::new((self.max.x - amount.x).max(0.0),
+                                  (self.max.y - amount.y).max(0.0));
+        Constraints::new(min, max)
+    }
+}
+
+/// Computes the size a gadget would like to have within the given constraints, the first pass
+/// of layout. The second pass is the container assigning `pos` and `dim`.
+pub type Measurer = Arc<dyn Fn(Gadget, Constraints)
+    -> Pin<Box<dyn Future<Output=ScalarPair> + Send + Sync>> + Send + Sync>;
+
+impl Gadget {
+    /// Desired size within `constraints` including the margin, with the gadget itself kept
+    /// within its own `min_dim` and `max_dim`. Gadgets without a measurer desire their preferred
+    /// dimension. Measurers get the constraints of the gadget without its margin.
+    pub async fn measure(&self, constraints: Constraints) -> ScalarPair {
+        let margin = self.margin.get_cloned().await.size();
+        let constraints = self.own_constraints().await.restrict(constraints.shrink(margin));
+        let measurer = self.measurer.get_cloned().await;
+        let desired = match measurer {
+            None => layout_preferred_dim(self).await,
+            Some(measurer) => measurer(self.clone(), constraints).await,
+        };
+        constraints.constrain(desired) + margin
+    }
+
+    pub async fn own_constraints(&self) -> Constraints {
+        Constraints::new(self.min_dim.get_cloned().await, self.max_dim.get_cloned().await)
+    }
+
+    pub async fn set_measurer<F>(&self, measurer: F)
+    where
+        F: Fn(Gadget, Constraints) -> Pin<Box<dyn Future<Output=ScalarPair> + Send + Sync>>
+            + Send + Sync + 'static,
+    {
+        self.measurer.set(Some(Arc::new(measurer))).await;
+        self.invalidate_measure().await;
+    }
+
+    /// Tells the container that the desired size may have changed, e.g. for a new caption.
+    pub async fn invalidate_measure(&self) {
+        let generation = self.measure_generation.get_cloned().await;
+        self.measure_generation.set(generation.wrapping_add(1)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tight_and_loose() {
+        let tight = Constraints::tight((5.0, 6.0));
+        assert_eq!(tight.min, tight.max);
+        assert_eq!(Constraints::loose((5.0, 6.0)).min, ScalarPair::zero());
+        assert_eq!(Constraints::unbounded().constrain((1e6, 1e6)), ScalarPair::new(1e6, 1e6));
+    }
+
+    #[test]
+    fn constrain_clamps_with_minimum_winning() {
+        let constraints = Constraints::new((10.0, 10.0), (100.0, 50.0));
+        assert_eq!(constraints.constrain((5.0, 80.0)), ScalarPair::new(10.0, 50.0));
+        assert_eq!(constraints.constrain((30.0, 30.0)), ScalarPair::new(30.0, 30.0));
+        let contradicting = Constraints::new((50.0, 50.0), (20.0, 20.0));
+        assert_eq!(contradicting.constrain((30.0, 30.0)), ScalarPair::new(50.0, 50.0));
+    }
+
+    #[test]
+    fn restrict_narrows_within_bounds() {
+        let outer = Constraints::loose((100.0, 100.0));
+        let restricted = outer.restrict(Constraints::new((20.0, 30.0), (80.0, 200.0)));
+        assert_eq!(restricted, Constraints::new((20.0, 30.0), (80.0, 100.0)));
+        // A maximum below the minimum is raised to it
+        let restricted = outer.restrict(Constraints::new((60.0, 60.0), (40.0, 40.0)));
+        assert_eq!(restricted, Constraints::new((60.0, 60.0), (60.0, 60.0)));
+    }
+
+    #[test]
+    fn shrink_stops_at_zero() {
+        let shrunk = Constraints::new((10.0, 10.0), (100.0, 50.0)).shrink((20.0, 20.0));
+        assert_eq!(shrunk, Constraints::new((0.0, 0.0), (80.0, 30.0)));
+    }
+}
@@ -20,6 +20,15 @@ pub mod drag;
 pub mod text;
 pub mod value;
 pub mod event;
+pub mod query;
+pub mod overlay;
+pub mod tooltip;
+pub mod stack;
+pub mod flex;
+pub mod grid;
+pub mod anchor;
+pub mod measure;
+pub mod scroll;
 
 #[macro_export]
 macro_rules! deref_to_super {
@@ -47,18 +56,35 @@ macro_rules! as_clone {
 #[macro_export]
 macro_rules! bit_flags {
     (pub enum $type_name:ident : $num_ty:ty { $($variant:ident = $value:literal),*, }) => {
-        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
         #[repr(transparent)]
         pub struct $type_name($num_ty);
         impl $type_name {
             $(
-            const $variant: Self = Self($value);
+            pub const $variant: Self = Self($value);
             paste::paste! {
-                pub fn [<has_ $variant>](self) -> bool {
-                    self.0 & Self::$variant.0 != 0
+                pub fn [<has_ $variant:lower>](self) -> bool {
+                    self.contains(Self::$variant)
                 }
             }
             )*
+
+            pub const fn empty() -> Self {
+                Self(0)
+            }
+
+            pub fn is_empty(self) -> bool {
+                self.0 == 0
+            }
+
+            /// Whether every flag set in `other` is set in `self`.
+            pub fn contains(self, other: Self) -> bool {
+                self.0 & other.0 == other.0
+            }
+
+            pub fn bits(self) -> $num_ty {
+                self.0
+            }
         }
         impl std::ops::BitOr for $type_name {
             type Output = Self;
@@ -67,6 +93,23 @@ macro_rules! bit_flags {
                 Self(self.0 | rhs.0)
             }
         }
+        impl std::ops::BitOrAssign for $type_name {
+            fn bitor_assign(&mut self, rhs: Self) {
+                self.0 |= rhs.0;
+            }
+        }
+        impl std::ops::BitAnd for $type_name {
+            type Output = Self;
+
+            fn bitand(self, rhs: Self) -> Self::Output {
+                Self(self.0 & rhs.0)
+            }
+        }
+        impl std::ops::BitAndAssign for $type_name {
+            fn bitand_assign(&mut self, rhs: Self) {
+                self.0 &= rhs.0;
+            }
+        }
     };
 }
 
@@ -86,6 +129,13 @@ pub fn caribou_init() {
     info!("Caribou started");
 }
 
+/// Starts the runtime for unit tests, once for all of them as they share the process.
+#[cfg(test)]
+pub(crate) fn caribou_test_init() {
+    static INIT: std::sync::Once = std::sync::Once::new();
+    INIT.call_once(|| unsafe { TOKIO_RUNTIME = Some(Runtime::new().unwrap()); });
+}
+
 pub fn async_runtime() -> &'static Runtime {
     unsafe { TOKIO_RUNTIME.as_ref().unwrap_unchecked() }
 }
@@ -0,0 +1,346 @@
+use crate::caribou::gadget::{Gadget, gadget_update_attachment, GadgetParent, GadgetRef};
+use crate::caribou::layout::{layout_child_local_pos, layout_child_transform};
+use crate::caribou::math::{Region, Scalar, ScalarPair};
+use crate::caribou::window::Window;
+
+/// Where an overlay goes relative to its anchor. When the preferred side does not fit into the
+/// window the overlay is flipped to the opposite side, and in any case clamped to the window.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Placement {
+    Below,
+    Above,
+    Right,
+    Left,
+    /// At a point in window space, e.g. the pointer position for a context menu.
+    Point(ScalarPair),
+}
+
+#[derive(Clone)]
+pub struct OverlayEntry {
+    pub gadget: Gadget,
+    pub anchor: Option<GadgetRef>,
+    pub placement: Placement,
+    pub offset: ScalarPair,
+    pub light_dismiss: bool,
+}
+
+impl PartialEq for OverlayEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.gadget == other.gadget
+    }
+}
+
+impl OverlayEntry {
+    pub fn new(gadget: Gadget) -> Self {
+        Self {
+            gadget,
+            anchor: None,
+            placement: Placement::Below,
+            offset: ScalarPair::zero(),
+            light_dismiss: false,
+        }
+    }
+
+    pub fn anchor(self, anchor: &Gadget) -> Self {
+        Self {
+            anchor: Some(anchor.refer()),
+            ..self
+        }
+    }
+
+    pub fn placement(self, placement: Placement) -> Self {
+        Self {
+            placement,
+            ..self
+        }
+    }
+
+    pub fn offset<S: Into<ScalarPair>>(self, offset: S) -> Self {
+        Self {
+            offset: offset.into(),
+            ..self
+        }
+    }
+
+    pub fn light_dismiss(self, light_dismiss: bool) -> Self {
+        Self {
+            light_dismiss,
+            ..self
+        }
+    }
+}
+
+pub struct Overlay;
+
+impl Overlay {
+    /// Puts a gadget on top of everything else in the window. Showing an already shown gadget
+    /// moves it to the top.
+    pub async fn show(window: &Window, entry: OverlayEntry) {
+        Overlay::dismiss(window, &entry.gadget).await;
+        let gadget = entry.gadget.clone();
+        gadget.parent.set(GadgetParent::Window(window.refer())).await;
+        overlay_position(window, &entry).await;
+
+        let wr = window.refer();
+        gadget.batch.listen(
+            "overlay_update",
+            move |event| {
+                let window = wr.get().unwrap();
+                Box::pin(async move {
+                    let gadget = event.gadget.get().unwrap();
+                    // Finer damage reported by the overlay itself is picked up by the window
+                    if gadget.damage.get_vec().await.is_empty() {
+                        overlay_damage(&window, &gadget).await;
+                    }
+                    window.request_redraw();
+                })
+            }).await;
+
+        let wr = window.refer();
+        gadget.pos.listen(
+            "overlay_damage",
+            move |event| {
+                let window = wr.get().unwrap();
+                Box::pin(async move {
+                    let gadget = event.gadget.get().unwrap();
+                    let current = overlay_bounds(&gadget).await;
+                    let delta = *event.old_value - event.state.get_cloned().await;
+                    window.report_damage(
+                        Region::from_origin_size(current.origin + delta, current.size)).await;
+                    window.report_damage(current).await;
+                    window.request_redraw();
+                })
+            }).await;
+
+        let wr = window.refer();
+        gadget.dim.listen(
+            "overlay_position",
+            move |event| {
+                let window = wr.get().unwrap();
+                Box::pin(async move {
+                    let gadget = event.gadget.get().unwrap();
+                    let entry = window.overlays.get_vec().await.iter()
+                        .find(|entry| entry.gadget == gadget)
+                        .cloned();
+                    if let Some(entry) = entry {
+                        let old_bounds = layout_child_transform(&gadget).await
+                            .map_region(Region::from_origin_size(
+                                ScalarPair::zero(), *event.old_value));
+                        window.report_damage(old_bounds).await;
+                        overlay_position(&window, &entry).await;
+                    }
+                })
+            }).await;
+
+        window.overlays.push(entry).await;
+        gadget_update_attachment(&gadget, Some(window.refer())).await;
+        overlay_damage(window, &gadget).await;
+        window.request_redraw();
+    }
+
+    pub async fn dismiss(window: &Window, gadget: &Gadget) {
+        let removed = window.overlays
+            .remove(&OverlayEntry::new(gadget.clone())).await;
+        if removed.is_none() {
+            return;
+        }
+        gadget.batch.remove_listener("overlay_update").await;
+        gadget.dim.remove_listener("overlay_position").await;
+        gadget.pos.remove_listener("overlay_damage").await;
+        gadget.take_damage().await;
+        overlay_damage(window, gadget).await;
+        gadget.mouse_down.clear().await;
+        gadget.mouse_pos.take().await;
+        gadget.parent.set(GadgetParent::None).await;
+        gadget_update_attachment(gadget, None).await;
+        window.request_redraw();
+    }
+
+    pub async fn dismiss_all(window: &Window) {
+        let overlays = window.overlays.get_vec().await.clone();
+        for entry in overlays.iter().rev() {
+            Overlay::dismiss(window, &entry.gadget).await;
+        }
+    }
+
+    /// Topmost overlay containing the point, with the point in its space.
+    pub async fn hit_test(window: &Window, pos: ScalarPair) -> Option<(Gadget, ScalarPair)> {
+        let overlays = window.overlays.get_vec().await.clone();
+        for entry in overlays.iter().rev() {
+            if let Some(local) = layout_child_local_pos(&entry.gadget, pos).await {
+                return Some((entry.gadget.clone(), local));
+            }
+        }
+        None
+    }
+
+    /// Dismisses light-dismiss overlays from the top down until one containing the point (or
+    /// one that is not light-dismiss) is met. `None` dismisses only the topmost one, which is
+    /// what Escape does.
+    pub async fn light_dismiss(window: &Window, pos: Option<ScalarPair>) {
+        loop {
+            let top = match window.overlays.get_vec().await.last() {
+                None => return,
+                Some(top) => top.clone(),
+            };
+            if !top.light_dismiss {
+                return;
+            }
+            match pos {
+                None => {
+                    Overlay::dismiss(window, &top.gadget).await;
+                    return;
+                }
+                Some(pos) => {
+                    if layout_child_local_pos(&top.gadget, pos).await.is_some() {
+                        return;
+                    }
+                    Overlay::dismiss(window, &top.gadget).await;
+                }
+            }
+        }
+    }
+
+    pub async fn reposition_all(window: &Window) {
+        let overlays = window.overlays.get_vec().await.clone();
+        for entry in overlays.iter() {
+            overlay_position(window, entry).await;
+        }
+    }
+}
+
+/// Bounds of an overlay in window space.
+async fn overlay_bounds(gadget: &Gadget) -> Region {
+    let dim = gadget.dim.get_cloned().await;
+    layout_child_transform(gadget).await
+        .map_region(Region::from_origin_size(ScalarPair::zero(), dim))
+}
+
+async fn overlay_damage(window: &Window, gadget: &Gadget) {
+    window.report_damage(overlay_bounds(gadget).await).await;
+}
+
+async fn overlay_position(window: &Window, entry: &OverlayEntry) {
+    let size = entry.gadget.dim.get_cloned().await;
+    let bounds = window.dim.get_cloned().await.to_scalar();
+    let anchor = match &entry.anchor {
+        None => None,
+        Some(anchor) => match anchor.get() {
+            None => None,
+            Some(anchor) => anchor.window_region().await,
+        },
+    };
+    let pos = match (anchor, entry.placement) {
+        (Some(anchor), placement) => overlay_place(placement, anchor, size, bounds),
+        (None, Placement::Point(point)) => overlay_place(
+            Placement::Point(point), Region::from_origin_size(point, ScalarPair::zero()),
+            size, bounds),
+        // Nothing to anchor to, center in the window
+        (None, _) => (bounds - size).times(0.5),
+    };
+    let pos = overlay_clamp(pos + entry.offset, size, bounds);
+    entry.gadget.pos.set(pos).await;
+}
+
+/// Keeps an overlay within the window, its top left corner winning when it does not fit.
+fn overlay_clamp(pos: ScalarPair, size: ScalarPair, bounds: ScalarPair) -> ScalarPair {
+    ScalarPair::new(
+        pos.x.min(bounds.x - size.x).max(0.0),
+        pos.y.min(bounds.y - size.y).max(0.0))
+}
+
+fn overlay_place(placement: Placement, anchor: Region, size: ScalarPair, bounds: ScalarPair)
+    -> ScalarPair
+{
+    let begin = anchor.origin;
+    let end = anchor.origin + anchor.size;
+    // Flip to the other side only if the preferred one overflows and the other one has more room
+    let flip = |preferred: Scalar, preferred_end: Scalar, limit: Scalar,
+                other: Scalar, other_room: Scalar| {
+        if preferred_end > limit && other_room > limit - preferred {
+            other
+        } else {
+            preferred
+        }
+    };
+    match placement {
+        Placement::Below => ScalarPair::new(
+            begin.x,
+            flip(end.y, end.y + size.y, bounds.y, begin.y - size.y, begin.y)),
+        Placement::Above => ScalarPair::new(
+            begin.x,
+            if begin.y - size.y < 0.0 && bounds.y - end.y > begin.y {
+                end.y
+            } else {
+                begin.y - size.y
+            }),
+        Placement::Right => ScalarPair::new(
+            flip(end.x, end.x + size.x, bounds.x, begin.x - size.x, begin.x),
+            begin.y),
+        Placement::Left => ScalarPair::new(
+            if begin.x - size.x < 0.0 && bounds.x - end.x > begin.x {
+                end.x
+            } else {
+                begin.x - size.x
+            },
+            begin.y),
+        Placement::Point(point) => ScalarPair::new(
+            flip(point.x, point.x + size.x, bounds.x, point.x - size.x, point.x),
+            flip(point.y, point.y + size.y, bounds.y, point.y - size.y, point.y)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOUNDS: ScalarPair = ScalarPair { x: 200.0, y: 100.0 };
+    const SIZE: ScalarPair = ScalarPair { x: 40.0, y: 30.0 };
+
+    fn anchor(x: Scalar, y: Scalar, w: Scalar, h: Scalar) -> Region {
+        Region::from_origin_size((x, y), (w, h))
+    }
+
+    #[test]
+    fn place_on_preferred_side_when_it_fits() {
+        let pos = overlay_place(Placement::Below, anchor(10.0, 20.0, 50.0, 10.0), SIZE, BOUNDS);
+        assert_eq!(pos, ScalarPair::new(10.0, 30.0));
+        let pos = overlay_place(Placement::Right, anchor(10.0, 20.0, 50.0, 10.0), SIZE, BOUNDS);
+        assert_eq!(pos, ScalarPair::new(60.0, 20.0));
+    }
+
+    #[test]
+    fn flip_when_overflowing_and_the_other_side_has_more_room() {
+        let pos = overlay_place(Placement::Below, anchor(10.0, 80.0, 50.0, 10.0), SIZE, BOUNDS);
+        assert_eq!(pos, ScalarPair::new(10.0, 50.0));
+        let pos = overlay_place(Placement::Above, anchor(10.0, 10.0, 50.0, 10.0), SIZE, BOUNDS);
+        assert_eq!(pos, ScalarPair::new(10.0, 20.0));
+        let pos = overlay_place(Placement::Left, anchor(10.0, 10.0, 50.0, 10.0), SIZE, BOUNDS);
+        assert_eq!(pos, ScalarPair::new(60.0, 10.0));
+    }
+
+    #[test]
+    fn keep_preferred_side_when_the_other_has_less_room() {
+        let pos = overlay_place(Placement::Below, anchor(10.0, 5.0, 50.0, 80.0), SIZE, BOUNDS);
+        assert_eq!(pos, ScalarPair::new(10.0, 85.0));
+    }
+
+    #[test]
+    fn flip_point_in_a_corner_on_both_axes() {
+        let point = ScalarPair::new(190.0, 95.0);
+        let pos = overlay_place(Placement::Point(point),
+                                Region::from_origin_size(point, ScalarPair::zero()), SIZE, BOUNDS);
+        assert_eq!(pos, ScalarPair::new(150.0, 65.0));
+    }
+
+    #[test]
+    fn clamp_into_window() {
+        assert_eq!(overlay_clamp(ScalarPair::new(10.0, 85.0), SIZE, BOUNDS),
+                   ScalarPair::new(10.0, 70.0));
+        assert_eq!(overlay_clamp(ScalarPair::new(180.0, -5.0), SIZE, BOUNDS),
+                   ScalarPair::new(160.0, 0.0));
+        // Too wide to fit, the left edge stays visible
+        assert_eq!(overlay_clamp(ScalarPair::new(10.0, 0.0), ScalarPair::new(300.0, 30.0), BOUNDS),
+                   ScalarPair::new(0.0, 0.0));
+    }
+}
@@ -0,0 +1,325 @@
+use crate::caribou::gadget::{Gadget, gadget_subtree, GadgetParent};
+use crate::caribou::window::Window;
+
+/// A parsed selector such as `Layout > Button.primary` or `#ok`.
+///
+/// Supported syntax: type names, `*`, `#id`, `.class`, the descendant combinator (whitespace)
+/// and the child combinator (`>`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Selector {
+    parts: Vec<(Combinator, Compound)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Combinator {
+    Descendant,
+    Child,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Compound {
+    type_name: Option<String>,
+    id: Option<String>,
+    classes: Vec<String>,
+}
+
+struct Identity {
+    type_name: &'static str,
+    id: Option<String>,
+    classes: Vec<String>,
+}
+
+impl Selector {
+    pub fn parse(source: &str) -> Option<Selector> {
+        let mut parts = Vec::new();
+        let mut chars = source.trim().chars().peekable();
+        let mut combinator = Combinator::Descendant;
+        while chars.peek().is_some() {
+            let mut compound = Compound::default();
+            let mut empty = true;
+            loop {
+                match chars.peek().copied() {
+                    Some('*') => {
+                        chars.next();
+                    }
+                    Some('#') => {
+                        chars.next();
+                        compound.id = Some(selector_ident(&mut chars)?);
+                    }
+                    Some('.') => {
+                        chars.next();
+                        compound.classes.push(selector_ident(&mut chars)?);
+                    }
+                    Some(c) if selector_ident_char(c) => {
+                        if !empty {
+                            return None;
+                        }
+                        compound.type_name = Some(selector_ident(&mut chars)?);
+                    }
+                    _ => break,
+                }
+                empty = false;
+            }
+            if empty {
+                return None;
+            }
+            parts.push((combinator, compound));
+            combinator = Combinator::Descendant;
+            while let Some(c) = chars.peek().copied() {
+                if c.is_whitespace() {
+                    chars.next();
+                } else if c == '>' && combinator == Combinator::Descendant {
+                    chars.next();
+                    combinator = Combinator::Child;
+                } else {
+                    break;
+                }
+            }
+            if combinator == Combinator::Child && chars.peek().is_none() {
+                return None;
+            }
+        }
+        if parts.is_empty() {
+            None
+        } else {
+            Some(Selector { parts })
+        }
+    }
+
+    /// Matches against a chain of identities, the candidate first and its ancestors after it.
+    fn matches(&self, chain: &[&Identity]) -> bool {
+        selector_match(&self.parts, chain)
+    }
+}
+
+fn selector_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-'
+}
+
+fn selector_ident(chars: &mut std::iter::Peekable<std::str::Chars>) -> Option<String> {
+    let mut ident = String::new();
+    while let Some(c) = chars.peek().copied() {
+        if !selector_ident_char(c) {
+            break;
+        }
+        ident.push(c);
+        chars.next();
+    }
+    if ident.is_empty() { None } else { Some(ident) }
+}
+
+fn selector_match(parts: &[(Combinator, Compound)], chain: &[&Identity]) -> bool {
+    let ((combinator, compound), rest) = match parts.split_last() {
+        None => return true,
+        Some(split) => split,
+    };
+    if chain.is_empty() || !compound.matches(chain[0]) {
+        return false;
+    }
+    if rest.is_empty() {
+        return true;
+    }
+    match combinator {
+        Combinator::Child => selector_match(rest, &chain[1..]),
+        Combinator::Descendant => (1..chain.len())
+            .any(|i| selector_match(rest, &chain[i..])),
+    }
+}
+
+impl Compound {
+    fn matches(&self, identity: &Identity) -> bool {
+        if let Some(type_name) = &self.type_name {
+            if type_name != identity.type_name {
+                return false;
+            }
+        }
+        if self.id.is_some() && self.id != identity.id {
+            return false;
+        }
+        self.classes.iter().all(|class| identity.classes.contains(class))
+    }
+}
+
+async fn gadget_identity(gadget: &Gadget) -> Identity {
+    Identity {
+        type_name: gadget.type_name.get_cloned().await,
+        id: gadget.id.get_cloned().await,
+        classes: gadget.classes.get_vec().await.clone(),
+    }
+}
+
+impl Gadget {
+    pub async fn has_class(&self, class: &str) -> bool {
+        self.classes.get_vec().await.iter().any(|c| c == class)
+    }
+
+    /// Parents of this gadget, nearest first.
+    pub async fn ancestors(&self) -> Vec<Gadget> {
+        let mut result = Vec::new();
+        let mut current = self.clone();
+        while let GadgetParent::Gadget(parent) = current.parent.get_cloned().await {
+            match parent.get() {
+                None => break,
+                Some(parent) => {
+                    result.push(parent.clone());
+                    current = parent;
+                }
+            }
+        }
+        result
+    }
+
+    /// All gadgets below this one in pre-order, excluding itself.
+    pub async fn descendants(&self) -> Vec<Gadget> {
+        let mut subtree = gadget_subtree(self).await;
+        subtree.remove(0);
+        subtree
+    }
+
+    pub async fn find_by_id(&self, id: &str) -> Option<Gadget> {
+        for gadget in gadget_subtree(self).await {
+            if gadget.id.get().await.as_deref() == Some(id) {
+                return Some(gadget);
+            }
+        }
+        None
+    }
+
+    pub async fn find_all_by_type(&self, type_name: &str) -> Vec<Gadget> {
+        let mut result = Vec::new();
+        for gadget in gadget_subtree(self).await {
+            if gadget.type_name.get_cloned().await == type_name {
+                result.push(gadget);
+            }
+        }
+        result
+    }
+
+    pub async fn find_all_by_class(&self, class: &str) -> Vec<Gadget> {
+        let mut result = Vec::new();
+        for gadget in gadget_subtree(self).await {
+            if gadget.has_class(class).await {
+                result.push(gadget);
+            }
+        }
+        result
+    }
+
+    /// Gadgets in this subtree (including itself) matching `selector`, in pre-order. Ancestors
+    /// above this gadget are not considered by combinators. `None` if the selector is invalid.
+    pub async fn query(&self, selector: &str) -> Option<Vec<Gadget>> {
+        let selector = Selector::parse(selector)?;
+        Some(self.query_selector(&selector).await)
+    }
+
+    pub async fn query_first(&self, selector: &str) -> Option<Gadget> {
+        self.query(selector).await?.into_iter().next()
+    }
+
+    pub async fn query_selector(&self, selector: &Selector) -> Vec<Gadget> {
+        struct Frame {
+            gadget: Gadget,
+            depth: usize,
+        }
+        let mut result = Vec::new();
+        // Identities of the current path from this gadget down to the candidate
+        let mut path: Vec<Identity> = Vec::new();
+        let mut stack = vec![Frame { gadget: self.clone(), depth: 0 }];
+        while let Some(Frame { gadget, depth }) = stack.pop() {
+            path.truncate(depth);
+            path.push(gadget_identity(&gadget).await);
+            let chain: Vec<&Identity> = path.iter().rev().collect();
+            if selector.matches(&chain) {
+                result.push(gadget.clone());
+            }
+            let children = gadget.children.get_vec().await.clone();
+            for child in children.into_iter().rev() {
+                stack.push(Frame { gadget: child, depth: depth + 1 });
+            }
+        }
+        result
+    }
+}
+
+impl Window {
+    pub async fn find_by_id(&self, id: &str) -> Option<Gadget> {
+        self.root.get_cloned().await.find_by_id(id).await
+    }
+
+    pub async fn find_all_by_type(&self, type_name: &str) -> Vec<Gadget> {
+        self.root.get_cloned().await.find_all_by_type(type_name).await
+    }
+
+    pub async fn find_all_by_class(&self, class: &str) -> Vec<Gadget> {
+        self.root.get_cloned().await.find_all_by_class(class).await
+    }
+
+    pub async fn query(&self, selector: &str) -> Option<Vec<Gadget>> {
+        self.root.get_cloned().await.query(selector).await
+    }
+
+    pub async fn query_first(&self, selector: &str) -> Option<Gadget> {
+        self.root.get_cloned().await.query_first(selector).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn identity(type_name: &'static str, id: Option<&str>, classes: &[&str]) -> Identity {
+        Identity {
+            type_name,
+            id: id.map(|id| id.to_string()),
+            classes: classes.iter().map(|class| class.to_string()).collect(),
+        }
+    }
+
+    fn matches(selector: &str, chain: &[&Identity]) -> bool {
+        Selector::parse(selector).unwrap().matches(chain)
+    }
+
+    #[test]
+    fn parse_valid() {
+        for source in ["Button", "*", "#ok", ".primary", "Button#ok.primary.big",
+                       "Layout > Button.primary", "Layout Stack>Button", "  Layout  "] {
+            assert!(Selector::parse(source).is_some(), "{}", source);
+        }
+        assert_eq!(Selector::parse("Layout>Button"), Selector::parse("Layout > Button"));
+    }
+
+    #[test]
+    fn parse_invalid() {
+        for source in ["", "   ", ">", "Button >", "Layout > > Button", "Button.", "#",
+                       "Button Layout#", "*Button", "Button, Layout"] {
+            assert!(Selector::parse(source).is_none(), "{}", source);
+        }
+    }
+
+    #[test]
+    fn match_compounds() {
+        let button = identity("Button", Some("ok"), &["primary"]);
+        assert!(matches("Button", &[&button]));
+        assert!(matches("*", &[&button]));
+        assert!(matches("#ok", &[&button]));
+        assert!(matches("Button#ok.primary", &[&button]));
+        assert!(!matches("Layout", &[&button]));
+        assert!(!matches("#cancel", &[&button]));
+        assert!(!matches(".primary.big", &[&button]));
+    }
+
+    #[test]
+    fn match_combinators() {
+        let layout = identity("Layout", None, &[]);
+        let stack = identity("Stack", None, &["toolbar"]);
+        let button = identity("Button", Some("ok"), &["primary"]);
+        let chain = [&button, &stack, &layout];
+        assert!(matches("Layout Button", &chain));
+        assert!(matches("Stack > Button.primary", &chain));
+        assert!(matches("Layout > .toolbar > #ok", &chain));
+        assert!(!matches("Layout > Button", &chain));
+        assert!(!matches("Button Layout", &chain));
+        // Only the candidate is matched by the last compound
+        assert!(!matches(".toolbar", &chain));
+    }
+}
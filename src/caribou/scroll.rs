@@ -0,0 +1,45 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use crate::caribou::gadget::Gadget;
+use crate::caribou::input::{Modifier2, ScrollDelta, ScrollEvent, TouchPhase};
+use crate::caribou::window::Window;
+
+/// Handles scrolling over a gadget, returning whether it was consumed. Events not consumed
+/// bubble up to the parent, e.g. when an inner scroll viewer has reached its end.
+pub type ScrollHandler = Arc<dyn Fn(Gadget, ScrollEvent)
+    -> Pin<Box<dyn Future<Output=bool> + Send + Sync>> + Send + Sync>;
+
+impl Gadget {
+    pub async fn set_scroll_handler<F>(&self, handler: F)
+    where
+        F: Fn(Gadget, ScrollEvent) -> Pin<Box<dyn Future<Output=bool> + Send + Sync>>
+            + Send + Sync + 'static,
+    {
+        self.scroll_handler.set(Some(Arc::new(handler))).await;
+    }
+}
+
+impl Window {
+    /// Gives a scroll at the pointer to the innermost gadget under it, bubbling up until a
+    /// handler consumes it. Returns whether any did. The modifiers are those held when the
+    /// scroll happened, which the window may not have caught up with yet.
+    pub async fn scroll(&self, delta: ScrollDelta, phase: TouchPhase, modifiers: Modifier2)
+                        -> bool {
+        let pos = match self.mouse_pos.get().await {
+            None => return false,
+            Some(pos) => pos,
+        };
+        let path = self.hit_path(pos).await;
+        for (gadget, local) in path.into_iter().rev() {
+            let handler = gadget.scroll_handler.get_cloned().await;
+            if let Some(handler) = handler {
+                let event = ScrollEvent { pos: local, delta, phase, modifiers };
+                if handler(gadget, event).await {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+}
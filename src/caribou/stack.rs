@@ -0,0 +1,123 @@
+use std::future::Future;
+use std::pin::Pin;
+use crate::caribou::gadget::Gadget;
+use crate::caribou::layout::{Alignment, Layout, layout_arrange_on, layout_place,
+                             layout_unwatch_child, layout_watch_child, Orientation};
+use crate::caribou::math::{Region, Scalar, ScalarPair};
+use crate::caribou::measure::Constraints;
+use crate::caribou::state::State;
+
+/// Container arranging its children in a line, one after another along its orientation.
+/// Children get their measured size on the main axis; on the cross axis they are placed by
+/// `alignment`, which may also stretch them to the available space.
+pub struct Stack;
+
+impl Stack {
+    pub async fn create(orientation: Orientation) -> Gadget {
+        let gadget = Layout::create().await;
+
+        // Fill specialized data
+        let data = StackData {
+            orientation: State::new(gadget.refer(), orientation),
+            spacing: State::new(gadget.refer(), 0.0),
+            alignment: State::new(gadget.refer(), Alignment::Start),
+        };
+        gadget.data.set_any(data.clone()).await;
+
+        // Fill common properties
+        gadget.type_name.set("Stack").await;
+        gadget.set_measurer(|stack, constraints| Box::pin(async move {
+            stack_measure(stack, constraints).await
+        })).await;
+
+        // Listen re-layout
+        gadget.children.listen_add(
+            "stack_children_add",
+            |event| Box::pin(async move {
+                let stack = event.gadget.get().unwrap();
+                layout_watch_child(&stack, &event.new_value, stack_arrange_boxed).await;
+                stack.invalidate_measure().await;
+                stack_arrange(stack).await;
+            })).await;
+
+        gadget.children.listen_remove(
+            "stack_children_remove",
+            |event| Box::pin(async move {
+                let stack = event.gadget.get().unwrap();
+                layout_unwatch_child(&event.old_value).await;
+                stack.invalidate_measure().await;
+                stack_arrange(stack).await;
+            })).await;
+
+        layout_arrange_on(&gadget.dim, "stack_arrange", stack_arrange_boxed, false).await;
+        layout_arrange_on(&data.orientation, "stack_arrange", stack_arrange_boxed, true).await;
+        layout_arrange_on(&data.spacing, "stack_arrange", stack_arrange_boxed, true).await;
+        layout_arrange_on(&gadget.padding, "stack_arrange", stack_arrange_boxed, true).await;
+        layout_arrange_on(&data.alignment, "stack_arrange", stack_arrange_boxed, false).await;
+
+        gadget
+    }
+}
+
+#[derive(Clone)]
+pub struct StackData {
+    pub orientation: State<Orientation>,
+    pub spacing: State<Scalar>,
+    pub alignment: State<Alignment>,
+}
+
+pub async fn stack_data(stack: &Gadget) -> StackData {
+    let data = stack.data.get_cloned().await;
+    let data = data.get::<StackData>().await;
+    (*data).clone()
+}
+
+/// Children along the main axis plus spacing, and the largest of them across it.
+async fn stack_measure(stack: Gadget, constraints: Constraints) -> ScalarPair {
+    let data = stack_data(&stack).await;
+    let orientation = data.orientation.get_cloned().await;
+    let spacing = data.spacing.get_cloned().await;
+    let padding = stack.padding.get_cloned().await;
+    let available = constraints.shrink(padding.size()).max;
+    let child_constraints = Constraints::loose(
+        orientation.pair(Scalar::INFINITY, orientation.cross(available)));
+
+    let children = stack.children.get_vec().await.clone();
+    let mut main = 0.0;
+    let mut cross: Scalar = 0.0;
+    for child in children.iter() {
+        let desired = child.measure(child_constraints).await;
+        main += orientation.main(desired);
+        cross = cross.max(orientation.cross(desired));
+    }
+    main += spacing * children.len().saturating_sub(1) as Scalar;
+    orientation.pair(main, cross) + padding.size()
+}
+
+fn stack_arrange_boxed(stack: Gadget) -> Pin<Box<dyn Future<Output=()> + Send + Sync>> {
+    Box::pin(stack_arrange(stack))
+}
+
+/// Children get their desired size along the main axis, whether or not they fit; what
+/// overflows is clipped by the stack.
+async fn stack_arrange(stack: Gadget) {
+    let data = stack_data(&stack).await;
+    let orientation = data.orientation.get_cloned().await;
+    let spacing = data.spacing.get_cloned().await;
+    let alignment = data.alignment.get_cloned().await;
+    let content = stack.content_region().await;
+    let available = orientation.cross(content.size);
+    let child_constraints = Constraints::loose(orientation.pair(Scalar::INFINITY, available));
+
+    let children = stack.children.get_vec().await.clone();
+    let mut offset = 0.0;
+    for child in children.iter() {
+        let desired = child.measure(child_constraints).await;
+        let main = orientation.main(desired);
+        let (cross_offset, cross) = alignment.place(orientation.cross(desired), available);
+        layout_place(child, Region::from_origin_size(
+            content.origin + orientation.pair(offset, cross_offset),
+            orientation.pair(main, cross))).await;
+        offset += main + spacing;
+    }
+}
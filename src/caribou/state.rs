@@ -258,9 +258,27 @@ impl<T: Send + Sync> State<T> {
         self.set(data.into()).await;
     }
 
+    /// Sets and notifies only if the value differs, returning whether it did. Used by
+    /// containers writing to their children, so that re-layouts do not feed back into
+    /// themselves.
+    pub async fn set_if_changed(&self, data: T) -> bool
+    where
+        T: PartialEq,
+    {
+        let mut lock = self.data.write().await;
+        if *lock == data {
+            return false;
+        }
+        let old = mem::replace(&mut *lock, data);
+        drop(lock);
+        self.notify(old).await;
+        true
+    }
+
     pub async fn listen(&self, name: &'static str, listener: impl Fn(StateChangedEvent<T>) ->
         Pin<Box<dyn Future<Output=()> + Send + Sync>> + Send + Sync + 'static) {
         self.listeners.write().await.push(Box::new(listener));
+        self.identities.write().await.push(name);
     }
 
     pub async fn remove_listener(&self, name: &'static str) {
@@ -477,6 +495,21 @@ impl<T: Send + Sync + Clone> OptionalState<T> {
     pub async fn set_from<U: Into<Option<T>>>(&self, data: U) {
         self.set(data.into()).await;
     }
+
+    /// Sets like [`OptionalState::set`], returning the value it replaced.
+    pub async fn replace(&self, data: Option<T>) -> Option<T> {
+        let mut lock = self.data.write().await;
+        let last_value = lock.take();
+        *lock = data.clone();
+        drop(lock);
+        match (last_value.clone(), data) {
+            (Some(last_value), Some(data)) => self.notify_change(last_value, data).await,
+            (Some(last_value), None) => self.notify_unset(last_value).await,
+            (None, Some(data)) => self.notify_set(data).await,
+            (None, None) => {}
+        }
+        last_value
+    }
     
     pub async fn listen_set(&self, name: &'static str, listener: impl Fn(OptionalStateSetEvent<T>)
         -> Pin<Box<dyn Future<Output=()> + Send + Sync>> + Send + Sync + 'static)
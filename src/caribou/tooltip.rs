@@ -0,0 +1,283 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::sync::RwLock;
+use crate::caribou::{schedule, ScheduleResult};
+use crate::caribou::batch::{begin_draw, begin_paint, Brush, Colors, SolidColor, TextAlign, Transform};
+use crate::caribou::gadget::{Gadget, GadgetRef};
+use crate::caribou::input::Key;
+use crate::caribou::math::ScalarPair;
+use crate::caribou::overlay::{Overlay, OverlayEntry, Placement};
+use crate::caribou::state::{OptionalState, State};
+use crate::caribou::window::Window;
+use crate::cb_backend_skia_gl::text::skia_measure_text;
+
+#[derive(Clone)]
+pub enum Tooltip {
+    Text(String),
+    Gadget(Gadget),
+}
+
+impl From<&str> for Tooltip {
+    fn from(text: &str) -> Self {
+        Tooltip::Text(text.to_string())
+    }
+}
+
+impl From<String> for Tooltip {
+    fn from(text: String) -> Self {
+        Tooltip::Text(text)
+    }
+}
+
+impl From<Gadget> for Tooltip {
+    fn from(gadget: Gadget) -> Self {
+        Tooltip::Gadget(gadget)
+    }
+}
+
+const TOOLTIP_POINTER_OFFSET: ScalarPair = ScalarPair { x: 12.0, y: 18.0 };
+const TOOLTIP_PADDING: ScalarPair = ScalarPair { x: 8.0, y: 4.0 };
+
+#[derive(Clone)]
+pub struct CaribouTooltip {
+    pub delay: State<Duration>,
+    pub shown: OptionalState<Gadget>,
+    owner: Arc<RwLock<Option<GadgetRef>>>,
+    generation: Arc<AtomicUsize>,
+}
+
+impl Default for CaribouTooltip {
+    fn default() -> Self {
+        CaribouTooltip {
+            delay: State::new(GadgetRef::default(), Duration::from_millis(500)),
+            shown: OptionalState::new_empty(GadgetRef::default()),
+            owner: Arc::new(RwLock::new(None)),
+            generation: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+}
+
+enum TooltipAnchor {
+    Pointer,
+    Gadget(Gadget),
+}
+
+impl CaribouTooltip {
+    pub async fn attach_listeners(&self, window: &Window) {
+        let wr = window.refer();
+        window.mouse_pos.listen_set(
+            "cb_tooltip",
+            move |event| {
+                let window = wr.get().unwrap();
+                Box::pin(async move {
+                    window.cb_tooltip.pointer_moved(&window, event.value).await;
+                })
+            }).await;
+
+        let wr = window.refer();
+        window.mouse_pos.listen_change(
+            "cb_tooltip",
+            move |event| {
+                let window = wr.get().unwrap();
+                Box::pin(async move {
+                    window.cb_tooltip.pointer_moved(&window, event.new_value).await;
+                })
+            }).await;
+
+        let wr = window.refer();
+        window.mouse_pos.listen_unset(
+            "cb_tooltip",
+            move |_| {
+                let window = wr.get().unwrap();
+                Box::pin(async move {
+                    let tooltip = &window.cb_tooltip;
+                    *tooltip.owner.write().await = None;
+                    tooltip.hide(&window).await;
+                })
+            }).await;
+
+        let wr = window.refer();
+        window.mouse_down.listen_add(
+            "cb_tooltip",
+            move |_| {
+                let window = wr.get().unwrap();
+                Box::pin(async move {
+                    window.cb_tooltip.hide(&window).await;
+                })
+            }).await;
+
+        let wr = window.refer();
+        window.key_down.listen_add(
+            "cb_tooltip",
+            move |event| {
+                let window = wr.get().unwrap();
+                Box::pin(async move {
+                    // Tab moves the focus, which brings up the tooltip of the next gadget
+                    if event.new_value != Key::Tab {
+                        window.cb_tooltip.hide(&window).await;
+                    }
+                })
+            }).await;
+
+        let wr = window.refer();
+        window.cb_focus.focused.listen_set(
+            "cb_tooltip",
+            move |event| {
+                let window = wr.get().unwrap();
+                Box::pin(async move {
+                    window.cb_tooltip.focus_moved(&window, event.value).await;
+                })
+            }).await;
+
+        let wr = window.refer();
+        window.cb_focus.focused.listen_change(
+            "cb_tooltip",
+            move |event| {
+                let window = wr.get().unwrap();
+                Box::pin(async move {
+                    window.cb_tooltip.focus_moved(&window, event.new_value).await;
+                })
+            }).await;
+
+        let wr = window.refer();
+        window.cb_focus.focused.listen_unset(
+            "cb_tooltip",
+            move |_| {
+                let window = wr.get().unwrap();
+                Box::pin(async move {
+                    window.cb_tooltip.hide(&window).await;
+                })
+            }).await;
+    }
+
+    /// Hides the tooltip being shown and cancels the pending one.
+    pub async fn hide(&self, window: &Window) {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        if let Some(shown) = self.shown.take().await {
+            Overlay::dismiss(window, &shown).await;
+        }
+    }
+
+    async fn pointer_moved(&self, window: &Window, pos: ScalarPair) {
+        let owner = tooltip_owner_at(window, pos).await;
+        let mut current = self.owner.write().await;
+        if current.as_ref().and_then(|owner| owner.get()) == owner {
+            return;
+        }
+        *current = owner.as_ref().map(|owner| owner.refer());
+        drop(current);
+        self.hide(window).await;
+        if let Some(owner) = owner {
+            self.schedule(window, owner, TooltipAnchor::Pointer).await;
+        }
+    }
+
+    async fn focus_moved(&self, window: &Window, focused: GadgetRef) {
+        // The tooltip of the gadget losing the focus goes even if the next one has none
+        *self.owner.write().await = None;
+        self.hide(window).await;
+        let focused = match focused.get() {
+            None => return,
+            Some(focused) => focused,
+        };
+        if focused.tooltip.get().await.is_none() {
+            return;
+        }
+        *self.owner.write().await = Some(focused.refer());
+        self.schedule(window, focused.clone(), TooltipAnchor::Gadget(focused)).await;
+    }
+
+    async fn schedule(&self, window: &Window, owner: Gadget, anchor: TooltipAnchor) {
+        let generation = self.generation.load(Ordering::SeqCst);
+        let delay = self.delay.get_cloned().await;
+        let wr = window.refer();
+        let owner = owner.refer();
+        let anchor = Arc::new(anchor);
+        schedule(delay, move || {
+            let wr = wr.clone();
+            let owner = owner.clone();
+            let anchor = anchor.clone();
+            Box::pin(async move {
+                let window = match wr.get() {
+                    None => return ScheduleResult::Break,
+                    Some(window) => window,
+                };
+                let tooltip = &window.cb_tooltip;
+                if tooltip.generation.load(Ordering::SeqCst) != generation {
+                    return ScheduleResult::Break;
+                }
+                let owner = match owner.get() {
+                    None => return ScheduleResult::Break,
+                    Some(owner) => owner,
+                };
+                let content = match owner.tooltip.get_cloned().await {
+                    None => return ScheduleResult::Break,
+                    Some(Tooltip::Text(text)) => tooltip_label(text).await,
+                    Some(Tooltip::Gadget(gadget)) => gadget,
+                };
+                let entry = match &*anchor {
+                    TooltipAnchor::Pointer => {
+                        let pos = match window.mouse_pos.get().await {
+                            None => return ScheduleResult::Break,
+                            Some(pos) => pos,
+                        };
+                        OverlayEntry::new(content.clone())
+                            .placement(Placement::Point(pos + TOOLTIP_POINTER_OFFSET))
+                    }
+                    TooltipAnchor::Gadget(gadget) => OverlayEntry::new(content.clone())
+                        .anchor(gadget)
+                        .placement(Placement::Below)
+                        .offset((0.0, 4.0)),
+                };
+                Overlay::show(&window, entry).await;
+                if let Some(previous) = tooltip.shown.replace(Some(content.clone())).await {
+                    Overlay::dismiss(&window, &previous).await;
+                }
+                // A hide while this was being shown found nothing to dismiss yet
+                if tooltip.generation.load(Ordering::SeqCst) != generation {
+                    if tooltip.shown.get().await.as_ref() == Some(&content) {
+                        tooltip.shown.take().await;
+                    }
+                    Overlay::dismiss(&window, &content).await;
+                }
+                ScheduleResult::Break
+            })
+        });
+    }
+}
+
+/// Nearest gadget with a tooltip under a point, walking up from the innermost one.
+async fn tooltip_owner_at(window: &Window, pos: ScalarPair) -> Option<Gadget> {
+    let hit = window.hit_test(pos).await?;
+    if hit.tooltip.get().await.is_some() {
+        return Some(hit);
+    }
+    for ancestor in hit.ancestors().await {
+        if ancestor.tooltip.get().await.is_some() {
+            return Some(ancestor);
+        }
+    }
+    None
+}
+
+async fn tooltip_label(text: String) -> Gadget {
+    let gadget = Gadget::default();
+    gadget.type_name.set("Tooltip").await;
+    let font = gadget.font.get_cloned().await;
+    let dim = skia_measure_text(&font, &text) + TOOLTIP_PADDING.times(2.0);
+    gadget.dim.set(dim).await;
+    let batch = begin_paint()
+        .path(Transform::default(),
+              begin_draw()
+                  .rect((0.0, 0.0), dim)
+                  .finish(),
+              Brush::from_stroke_fill(SolidColor::gray(0.7), SolidColor::gray(0.98)))
+        .text(Transform::from_translate(dim.times(0.5)),
+              text, font,
+              TextAlign::Center,
+              Brush::from_fill(Colors::BLACK))
+        .finish();
+    gadget.batch.set(batch).await;
+    gadget
+}
@@ -95,7 +95,7 @@ impl<T: Send + Clone> DerivedValue<T> {
         self.value.set(Some(value)).await;
     }
 
-    pub async fn hook<U: Send + Clone + 'static>(&self, other: &Value<U>) where T: 'static {
+    pub async fn hook<U: Send + Clone + 'static>(&self, other: &Value<U>) where T: Sync + 'static {
         let value = self.clone();
         other.listen(move |_| {
             as_clone!(value);
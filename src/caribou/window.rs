@@ -1,12 +1,19 @@
 use std::fmt::{Debug, Formatter, Pointer};
 use std::ops::Deref;
-use std::sync::{Arc, Weak};
+use std::sync::{Arc, Mutex, Weak};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use log::info;
 use crate::caribou::focus::CaribouFocus;
-use crate::caribou::gadget::{Gadget, GadgetParent, GadgetRef};
-use crate::caribou::input::{Key, MouseButton};
-use crate::caribou::math::{IntPair, ScalarPair};
+use crate::caribou::gadget::{Gadget, gadget_button_push, gadget_button_remove, gadget_pointer_put,
+                             gadget_pointer_take, gadget_update_attachment, GadgetParent, GadgetRef};
+use crate::caribou::batch::{Batch, begin_paint, Transform};
+use crate::caribou::event::Event;
+use crate::caribou::input::{CursorIcon, Key, KeyEvent, Modifier2, MouseButton};
+use crate::caribou::layout::{layout_child_local_pos, layout_child_transform};
+use crate::caribou::overlay::{Overlay, OverlayEntry};
+use crate::caribou::math::{IntPair, Region, ScalarPair};
 use crate::caribou::state::{OptionalState, State, StateVec};
+use crate::caribou::tooltip::CaribouTooltip;
 
 #[repr(transparent)]
 #[derive(Clone)]
@@ -34,6 +41,12 @@ impl WindowRef {
     }
 }
 
+impl PartialEq for WindowRef {
+    fn eq(&self, other: &Self) -> bool {
+        Weak::ptr_eq(&self.inner, &other.inner)
+    }
+}
+
 impl Deref for Window {
     type Target = WindowInner;
 
@@ -51,11 +64,22 @@ pub struct WindowInner {
     pub mouse_down: StateVec<MouseButton>,
     pub mouse_pos: OptionalState<ScalarPair>,
     pub key_down: StateVec<Key>,
+    /// Modifiers held now, as the platform reports them.
+    pub modifiers: State<Modifier2>,
+    pub overlays: StateVec<OverlayEntry>,
+    pub cursor: State<CursorIcon>,
+    pub damage: StateVec<Region>,
     // Mechanisms
     pub cb_focus: CaribouFocus,
+    pub cb_tooltip: CaribouTooltip,
     backend: Backend,
+    /// Size last reported by the backend, which is not sent back to it.
+    reported_dim: Mutex<Option<IntPair>>,
+    /// Identity of the root batch when the damage was last taken.
+    damage_root_batch: AtomicUsize,
     // Events
-    //pub key: Event<dyn Fn(KeyEventInfo) -> AsyncTask<()> + Send + Sync>,
+    /// Tells every key pressed, repeated or released, after `key_down` has been updated.
+    pub on_key: Event<KeyEvent>,
 }
 
 pub struct Backend {
@@ -78,6 +102,38 @@ pub trait WindowImpl: Send + Sync {
     fn debug_fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result;
     fn launch(&self, window: Window);
     fn request_redraw(&self);
+    fn set_cursor(&self, cursor: CursorIcon);
+    /// Resizes the window to a logical size. The backend reports the size it ends up with back
+    /// with `Window::report_size`.
+    fn set_size(&self, dim: IntPair);
+}
+
+/// Backend that only records what it is asked to do, for driving windows in tests.
+#[derive(Debug, Default, Clone)]
+pub struct RecordingWindowImpl {
+    pub redraws: Arc<AtomicUsize>,
+    pub cursors: Arc<Mutex<Vec<CursorIcon>>>,
+    pub sizes: Arc<Mutex<Vec<IntPair>>>,
+}
+
+impl WindowImpl for RecordingWindowImpl {
+    fn debug_fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.fmt(f)
+    }
+
+    fn launch(&self, _window: Window) {}
+
+    fn request_redraw(&self) {
+        self.redraws.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn set_cursor(&self, cursor: CursorIcon) {
+        self.cursors.lock().unwrap().push(cursor);
+    }
+
+    fn set_size(&self, dim: IntPair) {
+        self.sizes.lock().unwrap().push(dim);
+    }
 }
 
 impl Window {
@@ -92,14 +148,41 @@ impl Window {
                 mouse_down: StateVec::new(dummy.clone()),
                 mouse_pos: OptionalState::new_empty(dummy.clone()),
                 key_down: Default::default(),
+                modifiers: State::new(dummy.clone(), Modifier2::empty()),
+                overlays: StateVec::new(dummy.clone()),
+                cursor: State::new(dummy.clone(), CursorIcon::Default),
+                damage: StateVec::new(dummy.clone()),
                 cb_focus: CaribouFocus::default(),
+                cb_tooltip: CaribouTooltip::default(),
                 backend,
+                reported_dim: Mutex::new(None),
+                damage_root_batch: AtomicUsize::new(0),
+                on_key: Event::new(),
             })
         };
         window.cb_focus.attach_tab_listener(&window).await;
+        window_input_setup(&window).await;
+        window.cb_tooltip.attach_listeners(&window).await;
 
         window_root_setup(window.clone(), root.clone()).await;
 
+        let wr = window.refer();
+        window.dim.listen("window_resize", move |_| {
+            let wr = wr.clone();
+            Box::pin(async move {
+                let window = wr.get().unwrap();
+                let dim = window.dim.get_cloned().await;
+                if *window.reported_dim.lock().unwrap() != Some(dim) {
+                    window.backend.window_impl.set_size(dim);
+                }
+                window.root.get_cloned().await
+                    .dim.set_if_changed(dim.to_scalar()).await;
+                window.report_damage(Region::from_origin_size(ScalarPair::zero(), dim.to_scalar()))
+                    .await;
+                window.request_redraw();
+            })
+        }).await;
+
         let wr = window.refer();
         window.root.listen("root_switch", move |event| {
             let wr = wr.clone();
@@ -133,10 +216,124 @@ impl Window {
     pub fn request_redraw(&self) {
         self.backend.window_impl.request_redraw()
     }
+
+    /// Resolves the cursor for the gadget under the pointer again, for gadgets changing their
+    /// cursor while hovered.
+    pub async fn refresh_cursor(&self) {
+        let cursor = match self.mouse_pos.get().await {
+            None => return,
+            Some(pos) => match self.hit_test(pos).await {
+                None => CursorIcon::Default,
+                Some(hit) => hit.resolve_cursor().await,
+            },
+        };
+        if self.cursor.get_cloned().await != cursor {
+            self.cursor.set(cursor).await;
+        }
+    }
+
+    /// The innermost gadget under a point in window space, overlays taking priority.
+    pub async fn hit_test(&self, pos: ScalarPair) -> Option<Gadget> {
+        self.hit_path(pos).await.pop().map(|(gadget, _)| gadget)
+    }
+
+    /// Every gadget under a point in window space from the outermost to the innermost, each
+    /// with the point in its own space.
+    pub async fn hit_path(&self, pos: ScalarPair) -> Vec<(Gadget, ScalarPair)> {
+        let (mut current, mut local) = match Overlay::hit_test(self, pos).await {
+            Some(hit) => hit,
+            None => {
+                let root = self.root.get_cloned().await;
+                let dim = root.dim.get_cloned().await;
+                if !Region::from_origin_size(ScalarPair::zero(), dim).contains(pos) {
+                    return Vec::new();
+                }
+                (root, pos)
+            }
+        };
+        let mut path = Vec::new();
+        'descend: loop {
+            path.push((current.clone(), local));
+            let children = current.children.get_vec().await.clone();
+            for child in children.iter().rev() {
+                if let Some(child_local) = layout_child_local_pos(child, local).await {
+                    current = child.clone();
+                    local = child_local;
+                    continue 'descend;
+                }
+            }
+            return path;
+        }
+    }
+
+    /// Marks a region in window space as changed, for things not composed by the root such as
+    /// overlays coming and going.
+    /// Takes the logical size the backend's window ended up with, resizing the root along with
+    /// it without asking the backend for that size again.
+    pub async fn report_size(&self, dim: IntPair) {
+        *self.reported_dim.lock().unwrap() = Some(dim);
+        self.dim.set_if_changed(dim).await;
+    }
+
+    pub async fn report_damage(&self, region: Region) {
+        self.damage.push(region).await;
+    }
+
+    /// Takes everything damaged since the last call as a single region in window space. `None`
+    /// when nothing was reported, or when the root batch changed without the root reporting
+    /// damage, in which case the whole window should be redrawn.
+    pub async fn take_damage(&self) -> Option<Region> {
+        let mut regions = std::mem::take(&mut *self.damage.get_vec_mut().await);
+        let root = self.root.get_cloned().await;
+        let root_damage = root.take_damage().await;
+        let root_batch = root.batch.get().await.identity();
+        let root_changed = self.damage_root_batch.swap(root_batch, Ordering::SeqCst) != root_batch;
+        let root_unreported = root_changed && root_damage.is_empty();
+        regions.extend(root_damage);
+        let overlays = self.overlays.get_vec().await.clone();
+        for entry in overlays.iter() {
+            let transform = layout_child_transform(&entry.gadget).await;
+            for region in entry.gadget.take_damage().await {
+                regions.push(transform.map_region(region));
+            }
+        }
+        if root_unreported {
+            return None;
+        }
+        regions.into_iter().reduce(|acc, region| acc.union(region))
+    }
+
+    /// The batch to present: the root followed by the overlays, bottom to top.
+    pub async fn compose_batch(&self) -> Batch {
+        let mut artist = begin_paint()
+            .batch(Transform::default(), self.root.get().await.batch.get_cloned().await);
+        let overlays = self.overlays.get_vec().await.clone();
+        for entry in overlays.iter() {
+            artist = artist.batch(
+                layout_child_transform(&entry.gadget).await,
+                entry.gadget.batch.get_cloned().await);
+        }
+        artist.finish()
+    }
+
+    /// Takes a key event from the platform. Presses and releases update `key_down`, repeats leave
+    /// it as it is; every event is then told on `on_key`.
+    pub async fn key(&self, event: KeyEvent) {
+        if !event.is_down {
+            self.key_down.remove(&event.key).await;
+        } else if !event.repeat && !self.key_down.get_vec().await.contains(&event.key) {
+            self.key_down.push(event.key).await;
+        }
+        self.on_key.emit(event).await;
+    }
 }
 
 async fn window_root_setup(window: Window, root: Gadget) {
     root.parent.set(GadgetParent::Window(window.refer())).await;
+    gadget_update_attachment(&root, Some(window.refer())).await;
+    let dim = window.dim.get_cloned().await.to_scalar();
+    root.dim.set_if_changed(dim).await;
+    window.report_damage(Region::from_origin_size(ScalarPair::zero(), dim)).await;
 
     let wr = window.refer();
     root.batch.listen(
@@ -148,63 +345,422 @@ async fn window_root_setup(window: Window, root: Gadget) {
                 window.request_redraw();
             })
         }).await;
+}
+
+async fn window_root_setup_reverse(window: Window, root: Gadget) {
+    root.batch.remove_listener("window_update").await;
+    gadget_pointer_take(&root).await;
+    if let GadgetParent::Window(parent) = root.parent.get_cloned().await {
+        if parent == window.refer() {
+            root.parent.set(GadgetParent::None).await;
+        }
+    }
+    gadget_update_attachment(&root, None).await;
+}
+
+async fn window_input_setup(window: &Window) {
+    let wr = window.refer();
+    window.mouse_pos.listen_set(
+        "window_pointer_route",
+        move |event| {
+            let window = wr.get().unwrap();
+            Box::pin(async move {
+                window_route_pointer(&window, Some(event.value)).await;
+                window.refresh_cursor().await;
+            })
+        }).await;
+
+    let wr = window.refer();
+    window.mouse_pos.listen_change(
+        "window_pointer_route",
+        move |event| {
+            let window = wr.get().unwrap();
+            Box::pin(async move {
+                window_route_pointer(&window, Some(event.new_value)).await;
+                window.refresh_cursor().await;
+            })
+        }).await;
+
+    let wr = window.refer();
+    window.mouse_pos.listen_unset(
+        "window_pointer_route",
+        move |_| {
+            let window = wr.get().unwrap();
+            Box::pin(async move {
+                window_route_pointer(&window, None).await;
+            })
+        }).await;
 
-    let gr = root.refer();
+    let wr = window.refer();
     window.mouse_down.listen_add(
-        "mouse_down_add_sync",
+        "window_button_route",
         move |event| {
-            let gadget = gr.get().unwrap();
+            let window = wr.get().unwrap();
             Box::pin(async move {
-                gadget.mouse_down.push(event.new_value).await;
+                if let Some(pos) = window.mouse_pos.get().await {
+                    Overlay::light_dismiss(&window, Some(pos)).await;
+                    window_route_pointer(&window, Some(pos)).await;
+                }
+                for target in window_pointer_targets(&window).await {
+                    if target.mouse_pos.is_set().await {
+                        gadget_button_push(&target, event.new_value).await;
+                    }
+                }
             })
         }).await;
 
-    let gr = root.refer();
+    let wr = window.refer();
     window.mouse_down.listen_remove(
-        "mouse_down_remove_sync",
+        "window_button_route",
         move |event| {
-            let gadget = gr.get().unwrap();
+            let window = wr.get().unwrap();
             Box::pin(async move {
-                gadget.mouse_down.remove(&event.old_value).await;
+                for target in window_pointer_targets(&window).await {
+                    if target.mouse_pos.is_set().await {
+                        gadget_button_remove(&target, event.old_value).await;
+                    }
+                }
+                // The pointer goes back to whatever is under it
+                if window.mouse_down.get_vec().await.is_empty() {
+                    window_route_pointer(&window, window.mouse_pos.get().await).await;
+                    window.refresh_cursor().await;
+                }
             })
         }).await;
 
-    let gr = root.refer();
-    window.mouse_pos.listen_set(
-        "mouse_pos_set_sync",
+    let wr = window.refer();
+    window.key_down.listen_add(
+        "window_overlay_escape",
         move |event| {
-            let gadget = gr.get().unwrap();
+            let window = wr.get().unwrap();
             Box::pin(async move {
-                gadget.mouse_pos.put(event.value).await;
+                if event.new_value == Key::Escape {
+                    Overlay::light_dismiss(&window, None).await;
+                }
             })
         }).await;
 
-    let gr = root.refer();
-    window.mouse_pos.listen_unset(
-        "mouse_pos_unset_sync",
-        move |_| {
-            let gadget = gr.get().unwrap();
+    let wr = window.refer();
+    window.cursor.listen(
+        "window_cursor",
+        move |event| {
+            let window = wr.get().unwrap();
             Box::pin(async move {
-                gadget.mouse_pos.take().await;
+                let cursor = event.state.get_cloned().await;
+                window.backend.window_impl.set_cursor(cursor);
             })
         }).await;
 
-    let gr = root.refer();
-    window.mouse_pos.listen_change(
-        "mouse_pos_change_sync",
-        move |event| {
-            let gadget = gr.get().unwrap();
+    let wr = window.refer();
+    window.dim.listen(
+        "window_overlay_position",
+        move |_| {
+            let window = wr.get().unwrap();
             Box::pin(async move {
-                gadget.mouse_pos.put(event.new_value).await;
+                Overlay::reposition_all(&window).await;
             })
         }).await;
 }
 
-async fn window_root_setup_reverse(window: Window, root: Gadget) {
-    root.batch.remove_listener("window_update").await;
-    window.mouse_down.remove_listener_add("mouse_down_add_sync").await;
-    window.mouse_down.remove_listener_remove("mouse_down_remove_sync").await;
-    window.mouse_pos.remove_listener_set("mouse_pos_set_sync").await;
-    window.mouse_pos.remove_listener_unset("mouse_pos_unset_sync").await;
-    window.mouse_pos.remove_listener_change("mouse_pos_change_sync").await;
-}
\ No newline at end of file
+/// Everything that can receive the pointer: overlays from the top down, then the root.
+async fn window_pointer_targets(window: &Window) -> Vec<Gadget> {
+    let mut targets: Vec<Gadget> = window.overlays.get_vec().await.iter()
+        .rev()
+        .map(|entry| entry.gadget.clone())
+        .collect();
+    targets.push(window.root.get_cloned().await);
+    targets
+}
+
+/// Gives the pointer to the topmost overlay under it, or the root if there is none, and takes
+/// it from everything else. While buttons pressed over one of them are held, it keeps the
+/// pointer wherever it goes, as a child of a layout does.
+async fn window_route_pointer(window: &Window, pos: Option<ScalarPair>) {
+    let targets = window_pointer_targets(window).await;
+    let mut pressed = None;
+    for gadget in targets.iter() {
+        if gadget.mouse_pos.is_set().await && !gadget.mouse_down.get_vec().await.is_empty() {
+            pressed = Some(gadget.clone());
+            break;
+        }
+    }
+    let target = match (pos, pressed) {
+        (None, _) => None,
+        (Some(pos), Some(pressed)) => {
+            if pressed == window.root.get_cloned().await {
+                Some((pressed, pos))
+            } else {
+                layout_child_transform(&pressed).await.unmap_point(pos)
+                    .map(|local| (pressed, local))
+            }
+        }
+        (Some(pos), None) => match Overlay::hit_test(window, pos).await {
+            Some(hit) => Some(hit),
+            None => Some((window.root.get_cloned().await, pos)),
+        },
+    };
+    for gadget in targets {
+        match &target {
+            Some((target, local)) if target == &gadget => {
+                gadget_pointer_put(&gadget, *local).await;
+            }
+            _ => gadget_pointer_take(&gadget).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::Future;
+    use std::time::Duration;
+    use crate::caribou::{async_runtime, caribou_test_init};
+    use crate::caribou::layout::Layout;
+    use crate::caribou::input::MouseEvent;
+    use crate::caribou::overlay::Placement;
+    use super::*;
+
+    /// Waits for the listeners spawned by a change to catch up.
+    async fn eventually<F: Future<Output=bool>>(condition: impl Fn() -> F) {
+        for _ in 0..200 {
+            if condition().await {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        panic!("condition not reached");
+    }
+
+    fn last_cursor(recording: &RecordingWindowImpl) -> Option<CursorIcon> {
+        recording.cursors.lock().unwrap().last().copied()
+    }
+
+    #[test]
+    fn resolve_cursor_inherits_from_ancestors() {
+        caribou_test_init();
+        async_runtime().block_on(async {
+            let outer = Layout::create().await;
+            let inner = Layout::create().await;
+            let leaf = Gadget::default();
+            Layout::add_child(&outer, inner.clone()).await;
+            Layout::add_child(&inner, leaf.clone()).await;
+            assert_eq!(leaf.resolve_cursor().await, CursorIcon::Default);
+            outer.cursor.set(Some(CursorIcon::Move)).await;
+            assert_eq!(leaf.resolve_cursor().await, CursorIcon::Move);
+            inner.cursor.set(Some(CursorIcon::Text)).await;
+            assert_eq!(leaf.resolve_cursor().await, CursorIcon::Text);
+            leaf.cursor.set(Some(CursorIcon::Pointer)).await;
+            assert_eq!(leaf.resolve_cursor().await, CursorIcon::Pointer);
+        });
+    }
+
+    #[test]
+    fn cursor_follows_gadget_under_pointer() {
+        caribou_test_init();
+        async_runtime().block_on(async {
+            let recording = RecordingWindowImpl::default();
+            let root = Layout::create().await;
+            let child = Gadget::default();
+            child.pos.set(ScalarPair::new(10.0, 10.0)).await;
+            child.dim.set(ScalarPair::new(20.0, 20.0)).await;
+            child.cursor.set(Some(CursorIcon::Text)).await;
+            Layout::add_child(&root, child.clone()).await;
+            let window = Window::new(Backend::new(recording.clone()), root).await;
+
+            window.mouse_pos.put(ScalarPair::new(15.0, 15.0)).await;
+            eventually(|| async { last_cursor(&recording) == Some(CursorIcon::Text) }).await;
+
+            // Changing the cursor of the hovered gadget takes a refresh
+            child.cursor.set(Some(CursorIcon::Pointer)).await;
+            window.refresh_cursor().await;
+            eventually(|| async { last_cursor(&recording) == Some(CursorIcon::Pointer) }).await;
+
+            window.mouse_pos.put(ScalarPair::new(50.0, 50.0)).await;
+            eventually(|| async { last_cursor(&recording) == Some(CursorIcon::Default) }).await;
+        });
+    }
+
+    #[test]
+    fn press_keeps_pointer_over_overlays() {
+        caribou_test_init();
+        async_runtime().block_on(async {
+            let root = Layout::create().await;
+            let window = Window::new(Backend::new(RecordingWindowImpl::default()), root.clone())
+                .await;
+            window.dim.set(IntPair::new(100, 100)).await;
+            let overlay = Gadget::default();
+            overlay.dim.set(ScalarPair::new(20.0, 20.0)).await;
+            Overlay::show(&window, OverlayEntry::new(overlay.clone())
+                .placement(Placement::Point(ScalarPair::new(40.0, 40.0)))).await;
+
+            window.mouse_pos.put(ScalarPair::new(10.0, 10.0)).await;
+            eventually(|| root.mouse_pos.is_set()).await;
+            window.mouse_down.push(MouseButton::Primary).await;
+            eventually(|| async { !root.mouse_down.get_vec().await.is_empty() }).await;
+
+            // Dragging over the overlay leaves the pointer and the press with the root
+            window.mouse_pos.put(ScalarPair::new(45.0, 45.0)).await;
+            eventually(|| async {
+                root.mouse_pos.get().await == Some(ScalarPair::new(45.0, 45.0))
+            }).await;
+            assert!(!overlay.mouse_pos.is_set().await);
+            assert_eq!(*root.mouse_down.get_vec().await, vec![MouseButton::Primary]);
+
+            // Releasing gives it to the overlay under it
+            window.mouse_down.remove(&MouseButton::Primary).await;
+            eventually(|| overlay.mouse_pos.is_set()).await;
+            assert!(!root.mouse_pos.is_set().await);
+        });
+    }
+
+    #[test]
+    fn window_size_follows_both_ways() {
+        caribou_test_init();
+        async_runtime().block_on(async {
+            let recording = RecordingWindowImpl::default();
+            let root = Gadget::default();
+            let window = Window::new(Backend::new(recording.clone()), root.clone()).await;
+            assert_eq!(root.dim.get_cloned().await, ScalarPair::new(800.0, 600.0));
+
+            // Resizing from code asks the backend for the size
+            window.dim.set(IntPair::new(300, 200)).await;
+            eventually(|| async { root.dim.get_cloned().await == ScalarPair::new(300.0, 200.0) })
+                .await;
+            eventually(|| async {
+                recording.sizes.lock().unwrap().last() == Some(&IntPair::new(300, 200))
+            }).await;
+
+            // Sizes from the backend resize the root without being sent back to it
+            window.report_size(IntPair::new(640, 480)).await;
+            eventually(|| async { root.dim.get_cloned().await == ScalarPair::new(640.0, 480.0) })
+                .await;
+            assert_eq!(window.dim.get_cloned().await, IntPair::new(640, 480));
+            assert_eq!(*recording.sizes.lock().unwrap(), vec![IntPair::new(300, 200)]);
+        });
+    }
+
+    #[test]
+    fn unreported_root_batch_change_damages_everything() {
+        caribou_test_init();
+        async_runtime().block_on(async {
+            let root = Gadget::default();
+            let window = Window::new(Backend::new(RecordingWindowImpl::default()), root.clone())
+                .await;
+            let region = Region::from_origin_size(ScalarPair::zero(), ScalarPair::new(5.0, 5.0));
+            window.take_damage().await;
+
+            window.report_damage(region).await;
+            assert_eq!(window.take_damage().await, Some(region));
+
+            // The root drew something else without saying where
+            root.batch.set(begin_paint().finish()).await;
+            window.report_damage(region).await;
+            assert_eq!(window.take_damage().await, None);
+
+            window.report_damage(region).await;
+            assert_eq!(window.take_damage().await, Some(region));
+        });
+    }
+
+    #[test]
+    fn mouse_listeners_may_capture_the_pointer() {
+        caribou_test_init();
+        async_runtime().block_on(async {
+            let root = Layout::create().await;
+            let child = Gadget::default();
+            child.dim.set(ScalarPair::new(20.0, 20.0)).await;
+            Layout::add_child(&root, child.clone()).await;
+            let window = Window::new(Backend::new(RecordingWindowImpl::default()), root).await;
+            let moves = Arc::new(AtomicUsize::new(0));
+            let (cr, counter) = (child.refer(), moves.clone());
+            child.on_mouse.listen(move |event| {
+                let (cr, counter) = (cr.clone(), counter.clone());
+                Box::pin(async move {
+                    if let (MouseEvent::Move { .. }, Some(child)) = (event, cr.get()) {
+                        child.capture_pointer().await;
+                        counter.fetch_add(1, Ordering::SeqCst);
+                    }
+                })
+            }).await;
+
+            window.mouse_pos.put(ScalarPair::new(5.0, 5.0)).await;
+            eventually(|| async { moves.load(Ordering::SeqCst) == 1 }).await;
+            window.mouse_pos.put(ScalarPair::new(6.0, 6.0)).await;
+            eventually(|| async { moves.load(Ordering::SeqCst) == 2 }).await;
+        });
+    }
+
+    #[test]
+    fn mouse_listeners_may_release_the_pointer() {
+        caribou_test_init();
+        async_runtime().block_on(async {
+            let root = Layout::create().await;
+            let child = Gadget::default();
+            child.dim.set(ScalarPair::new(20.0, 20.0)).await;
+            Layout::add_child(&root, child.clone()).await;
+            let window = Window::new(Backend::new(RecordingWindowImpl::default()), root).await;
+            let (cr, moved) = (child.refer(), Arc::new(AtomicUsize::new(0)));
+            let counter = moved.clone();
+            child.on_mouse.listen(move |event| {
+                let (cr, counter) = (cr.clone(), counter.clone());
+                Box::pin(async move {
+                    match (event, cr.get()) {
+                        (MouseEvent::Enter, Some(child)) => child.capture_pointer().await,
+                        (MouseEvent::Move { .. }, Some(child)) => {
+                            child.release_capture().await;
+                            counter.fetch_add(1, Ordering::SeqCst);
+                        }
+                        _ => {}
+                    }
+                })
+            }).await;
+
+            window.mouse_pos.put(ScalarPair::new(5.0, 5.0)).await;
+            eventually(|| async { moved.load(Ordering::SeqCst) >= 1 }).await;
+            // Released while the pointer was given to it, the child does not keep it outside
+            window.mouse_pos.put(ScalarPair::new(50.0, 50.0)).await;
+            eventually(|| async { !child.mouse_pos.is_set().await }).await;
+        });
+    }
+
+    #[test]
+    fn losing_focus_lets_go_of_held_keys() {
+        caribou_test_init();
+        async_runtime().block_on(async {
+            let root = Layout::create().await;
+            let (first, second) = (Gadget::default(), Gadget::default());
+            Layout::add_child(&root, first.clone()).await;
+            Layout::add_child(&root, second.clone()).await;
+            let window = Window::new(Backend::new(RecordingWindowImpl::default()), root).await;
+            let press = KeyEvent {
+                key: Key::A,
+                is_down: true,
+                repeat: false,
+                modifiers: Modifier2::empty(),
+                scancode: 0,
+            };
+
+            window.cb_focus.focus(&first).await;
+            window.key(press.clone()).await;
+            assert_eq!(*first.key_down.get_vec().await, vec![Key::A]);
+
+            window.cb_focus.focus(&second).await;
+            assert!(first.key_down.get_vec().await.is_empty());
+            window.key(KeyEvent { is_down: false, ..press }).await;
+            assert!(second.key_down.get_vec().await.is_empty());
+        });
+    }
+
+    #[test]
+    fn refresh_cursor_without_pointer_keeps_cursor() {
+        caribou_test_init();
+        async_runtime().block_on(async {
+            let recording = RecordingWindowImpl::default();
+            let root = Layout::create().await;
+            root.cursor.set(Some(CursorIcon::Wait)).await;
+            let window = Window::new(Backend::new(recording.clone()), root).await;
+            window.refresh_cursor().await;
+            assert_eq!(window.cursor.get_cloned().await, CursorIcon::Default);
+            assert!(recording.cursors.lock().unwrap().is_empty());
+        });
+    }
+}
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+use skia_safe::{Picture, PictureRecorder};
 use crate::caribou::batch::{Batch, BatchOp, Brush, Material, Path, PathOp, TextAlign, Transform};
 
 type SkiaCanvas = skia_safe::Canvas;
@@ -10,55 +12,132 @@ type SkiaPoint = skia_safe::Point;
 type SkiaColor4f = skia_safe::Color4f;
 type SkiaPaintStyle = skia_safe::PaintStyle;
 
-pub fn skia_render_batch(canvas: &mut SkiaCanvas, batch: Batch) {
-    for op in batch.unwrap() {
+/// Recorded pictures of nested batches, keyed by the identity of their ops. A batch seen in two
+/// frames in a row is recorded and replayed from then on; entries not used in a frame are
+/// evicted at its end, as their batch has been replaced.
+#[derive(Default)]
+pub struct SkiaPictureCache {
+    entries: HashMap<usize, SkiaPictureEntry>,
+    frame: u64,
+}
+
+struct SkiaPictureEntry {
+    // Keeps the ops alive so that their address is not reused by another batch
+    batch: Batch,
+    picture: Option<Picture>,
+    last_used: u64,
+}
+
+impl SkiaPictureCache {
+    pub fn end_frame(&mut self) {
+        let frame = self.frame;
+        self.entries.retain(|_, entry| entry.last_used == frame);
+        self.frame += 1;
+    }
+}
+
+/// Like [`skia_render_batch`], but replays nested batches from `cache` when they are unchanged.
+pub fn skia_render_batch_cached(canvas: &mut SkiaCanvas, batch: &Batch,
+                                cache: &mut SkiaPictureCache) {
+    for op in batch.iter() {
         match op {
-            BatchOp::Path { transform, path, brush } => {
-                let checkpoint = canvas.save();
-                skia_apply_transform(canvas, transform);
-                let skia_path = skia_render_path(path);
-                let (stroke, fill) = skia_brush_to_stroke_fill_paint(brush);
-                canvas.draw_path(&skia_path, &fill);
-                canvas.draw_path(&skia_path, &stroke);
-                canvas.restore_to_count(checkpoint);
-            }
-            BatchOp::Image { transform, image } => {
-                let checkpoint = canvas.save();
-                skia_apply_transform(canvas, transform);
-                todo!();
-                canvas.restore_to_count(checkpoint);
-            }
-            BatchOp::Text(top) => {
-                let checkpoint = canvas.save();
-                skia_apply_transform(canvas, top.transform);
-                let font = top.font.get::<SkiaFont>().unwrap();
-                let (stroke, fill) =
-                    skia_brush_to_stroke_fill_paint(top.brush);
-                let (_, stroke_bounds) = font
-                    .measure_str(top.text.as_str(), Some(&stroke));
-                let (_, fill_bounds) = font
-                    .measure_str(top.text.as_str(), Some(&fill));
-                let mut bounds = stroke_bounds;
-                bounds.join(&fill_bounds);
-                let offset: SkiaPoint = match top.align {
-                    TextAlign::Origin => SkiaPoint::default(),
-                    TextAlign::Center => (-bounds.width() / 2.0,
-                                          bounds.height() / 2.0).into(),
-                };
-                canvas.draw_str(top.text.as_str(),
-                                offset,
-                                &font, &fill);
-                canvas.draw_str(top.text.as_str(),
-                                offset,
-                                &font, &stroke);
-                canvas.restore_to_count(checkpoint);
-            }
             BatchOp::Batch { transform, batch } => {
                 let checkpoint = canvas.save();
-                skia_apply_transform(canvas, transform);
-                skia_render_batch(canvas, batch);
+                skia_apply_transform(canvas, *transform);
+                match skia_cached_picture(batch, cache) {
+                    Some(picture) => canvas.draw_picture(&picture, None, None),
+                    None => skia_render_batch_cached(canvas, batch, cache),
+                };
                 canvas.restore_to_count(checkpoint);
             }
+            op => skia_render_op(canvas, op),
+        }
+    }
+}
+
+fn skia_cached_picture(batch: &Batch, cache: &mut SkiaPictureCache) -> Option<Picture> {
+    let frame = cache.frame;
+    let entry = cache.entries.entry(batch.identity())
+        .or_insert_with(|| SkiaPictureEntry {
+            batch: batch.clone(),
+            picture: None,
+            last_used: frame,
+        });
+    // Seen for the first time, it may well change again before the next frame
+    if entry.last_used == frame && entry.picture.is_none() {
+        return None;
+    }
+    entry.last_used = frame;
+    if let Some(picture) = &entry.picture {
+        return Some(picture.clone());
+    }
+    let batch = entry.batch.clone();
+    let mut recorder = PictureRecorder::new();
+    let bounds = SkiaRect::new(-SKIA_PICTURE_EXTENT, -SKIA_PICTURE_EXTENT,
+                               SKIA_PICTURE_EXTENT, SKIA_PICTURE_EXTENT);
+    skia_render_batch_cached(recorder.begin_recording(bounds, None), &batch, cache);
+    let picture = recorder.finish_recording_as_picture(None)?;
+    if let Some(entry) = cache.entries.get_mut(&batch.identity()) {
+        entry.picture = Some(picture.clone());
+    }
+    Some(picture)
+}
+
+const SKIA_PICTURE_EXTENT: f32 = 1.0e6;
+
+pub fn skia_render_batch(canvas: &mut SkiaCanvas, batch: &Batch) {
+    for op in batch.iter() {
+        skia_render_op(canvas, op);
+    }
+}
+
+pub fn skia_render_op(canvas: &mut SkiaCanvas, op: &BatchOp) {
+    match op {
+        BatchOp::Path { transform, path, brush } => {
+            let checkpoint = canvas.save();
+            skia_apply_transform(canvas, *transform);
+            let skia_path = skia_render_path(path.clone());
+            let (stroke, fill) = skia_brush_to_stroke_fill_paint(*brush);
+            canvas.draw_path(&skia_path, &fill);
+            canvas.draw_path(&skia_path, &stroke);
+            canvas.restore_to_count(checkpoint);
+        }
+        BatchOp::Image { transform, image } => {
+            let checkpoint = canvas.save();
+            skia_apply_transform(canvas, *transform);
+            todo!();
+            canvas.restore_to_count(checkpoint);
+        }
+        BatchOp::Text(top) => {
+            let checkpoint = canvas.save();
+            skia_apply_transform(canvas, top.transform);
+            let font = top.font.get::<SkiaFont>().unwrap();
+            let (stroke, fill) =
+                skia_brush_to_stroke_fill_paint(top.brush);
+            let (_, stroke_bounds) = font
+                .measure_str(top.text.as_str(), Some(&stroke));
+            let (_, fill_bounds) = font
+                .measure_str(top.text.as_str(), Some(&fill));
+            let mut bounds = stroke_bounds;
+            bounds.join(&fill_bounds);
+            let offset: SkiaPoint = match top.align {
+                TextAlign::Origin => SkiaPoint::default(),
+                TextAlign::Center => (-bounds.width() / 2.0,
+                                      bounds.height() / 2.0).into(),
+            };
+            canvas.draw_str(top.text.as_str(),
+                            offset,
+                            &font, &fill);
+            canvas.draw_str(top.text.as_str(),
+                            offset,
+                            &font, &stroke);
+            canvas.restore_to_count(checkpoint);
+        }
+        BatchOp::Batch { transform, batch } => {
+            let checkpoint = canvas.save();
+            skia_apply_transform(canvas, *transform);
+            skia_render_batch(canvas, batch);
+            canvas.restore_to_count(checkpoint);
         }
     }
 }
@@ -71,6 +150,13 @@ pub fn skia_apply_transform(canvas: &mut SkiaCanvas, transform: Transform) {
     canvas.rotate(transform.rotate,
                   Some((transform.rotate_center.x,
                         transform.rotate_center.y).into()));
+    if let Some(clip) = transform.clip {
+        canvas.clip_rect(SkiaRect::from_wh(clip.x, clip.y), None, Some(true));
+    }
+    if transform.opacity < 1.0 {
+        let alpha = (transform.opacity.clamp(0.0, 1.0) * 255.0).round();
+        canvas.save_layer_alpha(None, alpha as u32);
+    }
 }
 
 pub fn skia_brush_to_stroke_fill_paint(brush: Brush) -> (SkiaPaint, SkiaPaint) {
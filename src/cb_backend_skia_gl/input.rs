@@ -1,6 +1,8 @@
-use glutin::event::{ModifiersState, MouseButton, VirtualKeyCode};
+use glutin::event::{ModifiersState, MouseButton, MouseScrollDelta, TouchPhase, VirtualKeyCode};
+use glutin::window;
 use crate::caribou;
-use crate::caribou::input::{Key, Modifier};
+use crate::caribou::input::{CursorIcon, Key, Modifier2, ScrollDelta};
+use crate::caribou::math::ScalarPair;
 
 pub fn gl_virtual_to_key(vir: VirtualKeyCode) -> Key {
     match vir {
@@ -170,19 +172,19 @@ pub fn gl_virtual_to_key(vir: VirtualKeyCode) -> Key {
     }
 }
 
-pub fn gl_modifier_interpret(modifiers: ModifiersState) -> Vec<Modifier> {
-    let mut result = Vec::new();
+pub fn gl_modifier_interpret(modifiers: ModifiersState) -> Modifier2 {
+    let mut result = Modifier2::empty();
     if modifiers.shift() {
-        result.push(Modifier::Shift);
+        result |= Modifier2::SHIFT;
     }
     if modifiers.ctrl() {
-        result.push(Modifier::Control);
+        result |= Modifier2::CONTROL;
     }
     if modifiers.alt() {
-        result.push(Modifier::Alt);
+        result |= Modifier2::ALT;
     }
     if modifiers.logo() {
-        result.push(Modifier::Meta);
+        result |= Modifier2::META;
     }
     result
 }
@@ -197,4 +199,47 @@ pub fn gl_mouse_button_interpret(button: MouseButton) -> CbMB {
         MouseButton::Middle => CbMB::Tertiary,
         MouseButton::Other(n) => CbMB::Other(n),
     }
+}
+
+/// Wheels and touchpads report how far they moved, the content moves the other way. Pixels are
+/// physical and made logical by `scale_factor`.
+pub fn gl_scroll_delta_interpret(delta: MouseScrollDelta, scale_factor: f32) -> ScrollDelta {
+    match delta {
+        MouseScrollDelta::LineDelta(x, y) => ScrollDelta::Lines(ScalarPair::new(-x, -y)),
+        MouseScrollDelta::PixelDelta(position) => ScrollDelta::Pixels(
+            ScalarPair::new(-position.x as f32, -position.y as f32).times(1_f32 / scale_factor)),
+    }
+}
+
+type CbTP = caribou::input::TouchPhase;
+
+pub fn gl_touch_phase_interpret(phase: TouchPhase) -> CbTP {
+    match phase {
+        TouchPhase::Started => CbTP::Started,
+        TouchPhase::Moved => CbTP::Moved,
+        TouchPhase::Ended => CbTP::Ended,
+        TouchPhase::Cancelled => CbTP::Cancelled,
+    }
+}
+
+pub fn gl_cursor_icon(cursor: CursorIcon) -> window::CursorIcon {
+    match cursor {
+        CursorIcon::Default => window::CursorIcon::Default,
+        CursorIcon::Pointer => window::CursorIcon::Hand,
+        CursorIcon::Text => window::CursorIcon::Text,
+        CursorIcon::Crosshair => window::CursorIcon::Crosshair,
+        CursorIcon::Move => window::CursorIcon::Move,
+        CursorIcon::Wait => window::CursorIcon::Wait,
+        CursorIcon::Progress => window::CursorIcon::Progress,
+        CursorIcon::Help => window::CursorIcon::Help,
+        CursorIcon::NotAllowed => window::CursorIcon::NotAllowed,
+        CursorIcon::Grab => window::CursorIcon::Grab,
+        CursorIcon::Grabbing => window::CursorIcon::Grabbing,
+        CursorIcon::ResizeHorizontal => window::CursorIcon::EwResize,
+        CursorIcon::ResizeVertical => window::CursorIcon::NsResize,
+        CursorIcon::ResizeNwse => window::CursorIcon::NwseResize,
+        CursorIcon::ResizeNesw => window::CursorIcon::NeswResize,
+        CursorIcon::ResizeColumn => window::CursorIcon::ColResize,
+        CursorIcon::ResizeRow => window::CursorIcon::RowResize,
+    }
 }
\ No newline at end of file
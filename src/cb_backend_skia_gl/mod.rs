@@ -5,9 +5,13 @@ pub mod text;
 
 use std::fmt::{Debug, Formatter};
 
+use glutin::dpi::LogicalSize;
+
 use crate::caribou::gadget::Gadget;
-use crate::caribou::math::Scalar;
+use crate::caribou::input::CursorIcon;
+use crate::caribou::math::{IntPair, Scalar};
 use crate::caribou::window::{Backend, Window, WindowImpl};
+use crate::cb_backend_skia_gl::input::gl_cursor_icon;
 use crate::cb_backend_skia_gl::runtime::{ENV_REGISTRY, skia_gl_launch};
 
 pub async fn skia_gl_create_window(root: Gadget) -> Window {
@@ -33,6 +37,14 @@ impl WindowImpl for SkiaGLWindowImpl {
     fn request_redraw(&self) {
         skia_request_redraw(self.env_id);
     }
+
+    fn set_cursor(&self, cursor: CursorIcon) {
+        skia_set_cursor(self.env_id, cursor);
+    }
+
+    fn set_size(&self, dim: IntPair) {
+        skia_set_size(self.env_id, dim);
+    }
 }
 
 pub fn skia_request_redraw(env_id: usize) {
@@ -42,6 +54,21 @@ pub fn skia_request_redraw(env_id: usize) {
     };
 }
 
+pub fn skia_set_cursor(env_id: usize, cursor: CursorIcon) {
+    match ENV_REGISTRY.read().unwrap().get(env_id) {
+        None => {}
+        Some(env) => env.windowed_context.window().set_cursor_icon(gl_cursor_icon(cursor)),
+    };
+}
+
+pub fn skia_set_size(env_id: usize, dim: IntPair) {
+    match ENV_REGISTRY.read().unwrap().get(env_id) {
+        None => {}
+        Some(env) => env.windowed_context.window()
+            .set_inner_size(LogicalSize::new(dim.x as f64, dim.y as f64)),
+    };
+}
+
 type SkiaFont = skia_safe::Font;
 type SkiaFontStyle = skia_safe::FontStyle;
 
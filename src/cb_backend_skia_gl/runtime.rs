@@ -5,7 +5,7 @@ use std::sync::atomic::AtomicBool;
 
 use gl::types::*;
 use glutin::{
-    dpi::Position,
+    dpi::{LogicalSize, PhysicalSize, Position},
     window::{Window, WindowBuilder},
     event_loop::{ControlFlow, EventLoop},
     ContextWrapper,
@@ -21,25 +21,28 @@ use glutin::{
 };
 use glutin::event_loop::ControlFlow::WaitUntil;
 use log::{info};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
 use skia_safe::{
     gpu::gl::{Format, FramebufferInfo},
     gpu::{BackendRenderTarget, DirectContext, SurfaceOrigin},
     Color,
     ColorType,
+    Rect,
     Surface
 };
 use crate::{
     caribou::{
         async_runtime,
-        input::{Key}
+        input::{Key, KeyEvent, Modifier2, MouseButton, ScrollDelta, TouchPhase}
     },
     cb_backend_skia_gl::{
-        batch::skia_render_batch,
+        batch::{skia_render_batch_cached, SkiaPictureCache},
         input::gl_virtual_to_key
     }
 };
-use crate::caribou::math::ScalarPair;
-use crate::cb_backend_skia_gl::input::{gl_mouse_button_interpret};
+use crate::caribou::math::{IntPair, Region, ScalarPair};
+use crate::cb_backend_skia_gl::input::{gl_modifier_interpret, gl_mouse_button_interpret, gl_scroll_delta_interpret,
+                                       gl_touch_phase_interpret};
 
 type WindowedContext = ContextWrapper<PossiblyCurrent, Window>;
 
@@ -50,6 +53,7 @@ pub struct SkGLEnv2 {
     pub gr_context: RefCell<DirectContext>,
     pub windowed_context: WindowedContext,
     pub need_redraw: AtomicBool,
+    pub picture_cache: RefCell<SkiaPictureCache>,
 }
 
 unsafe impl Send for SkGLEnv2 {}
@@ -94,10 +98,73 @@ fn skia_gl_create_surface(
         .unwrap()
 }
 
+/// Tells the window its new logical size, which resizes the root along with it.
+fn skia_report_size(window: &CbWindow, physical_size: PhysicalSize<u32>) {
+    let logical: LogicalSize<f64> = physical_size.to_logical(skia_get_scale_factor() as f64);
+    let dim = IntPair::new(logical.width.round() as _, logical.height.round() as _);
+    let window = window.clone();
+    async_runtime().spawn(async move {
+        window.report_size(dim).await;
+    });
+}
+
+/// Input from the event loop, in the order it came in. Pointer input carries the modifiers held
+/// when it happened.
+enum SkiaInput {
+    Key(KeyEvent),
+    Modifiers(Modifier2),
+    PointerMoved(ScalarPair, Modifier2),
+    PointerLeft,
+    Button(MouseButton, bool, Modifier2),
+    Wheel(ScrollDelta, TouchPhase, Modifier2),
+}
+
+/// Delivers input to the window one event after another from a single task, as a task for each
+/// could run out of order on the threads of the runtime.
+fn skia_input_spawn(window: CbWindow) -> UnboundedSender<SkiaInput> {
+    let (sender, mut receiver) = unbounded_channel();
+    async_runtime().spawn(async move {
+        while let Some(input) = receiver.recv().await {
+            match input {
+                SkiaInput::Key(event) => {
+                    window.key(event).await;
+                }
+                SkiaInput::Modifiers(modifiers) => {
+                    window.modifiers.set_if_changed(modifiers).await;
+                }
+                SkiaInput::PointerMoved(pos, modifiers) => {
+                    window.modifiers.set_if_changed(modifiers).await;
+                    window.mouse_pos.put(pos).await;
+                }
+                SkiaInput::PointerLeft => {
+                    window.mouse_pos.take().await;
+                }
+                SkiaInput::Button(button, is_down, modifiers) => {
+                    window.modifiers.set_if_changed(modifiers).await;
+                    if is_down {
+                        window.mouse_down.push(button).await;
+                    } else {
+                        window.mouse_down.remove(&button).await;
+                    }
+                }
+                SkiaInput::Wheel(delta, phase, modifiers) => {
+                    window.scroll(delta, phase, modifiers).await;
+                }
+            }
+        }
+    });
+    sender
+}
+
 pub fn skia_gl_launch(window: CbWindow, env_id: usize) {
     info!("Launching Skia GL window");
     let el = EventLoop::new();
-    let wb = WindowBuilder::new().with_title("Caribou");
+    let (title, dim) = async_runtime().block_on(async {
+        (window.title.get_cloned().await, window.dim.get_cloned().await)
+    });
+    let wb = WindowBuilder::new()
+        .with_title(title)
+        .with_inner_size(LogicalSize::new(dim.x as f64, dim.y as f64));
 
     let cb = glutin::ContextBuilder::new()
         .with_depth_buffer(0)
@@ -148,10 +215,22 @@ pub fn skia_gl_launch(window: CbWindow, env_id: usize) {
         gr_context: gr_context.into(),
         windowed_context,
         need_redraw: AtomicBool::new(true),
+        picture_cache: RefCell::new(SkiaPictureCache::default()),
     }));
 
+    let input = skia_input_spawn(window.clone());
     let mut mouse_pos: ScalarPair = (0.0, 0.0).into();
-    let mut ret_vec: Vec<Key> = Vec::new();
+    // Keys held down and modifiers as last reported, kept here so that repeats are told apart
+    // and every key and pointer event carries the modifiers in order with it.
+    let mut pressed_keys: Vec<Key> = Vec::new();
+    let mut modifiers = Modifier2::empty();
+    // Buttons held down and whether the cursor is over the window, as a press keeps the pointer
+    // in the window until every button is released.
+    let mut pressed_buttons: Vec<MouseButton> = Vec::new();
+    let mut cursor_inside = false;
+    // Damage of the last frame, `None` if it was redrawn entirely. When the back buffer we draw
+    // into holds the frame before it, both frames' damage has to be repainted.
+    let mut previous_damage: Option<Region> = None;
 
     // TODO: Get FPS of monitor and use that as the max FPS
     let maximum_wait = std::time::Duration::from_millis(1000 / 60);
@@ -171,85 +250,84 @@ pub fn skia_gl_launch(window: CbWindow, env_id: usize) {
                         &env.windowed_context, &fb_info, &mut env.gr_context.borrow_mut());
                     drop(surface);
                     env.windowed_context.resize(physical_size);
+                    previous_damage = None;
+                    skia_report_size(&window, physical_size);
+                }
+                WindowEvent::ScaleFactorChanged { scale_factor, new_inner_size } => {
+                    let env = ENV_REGISTRY.read().unwrap()[env_id].clone();
+                    skia_set_scale_factor(scale_factor as f32);
+                    previous_damage = None;
+                    skia_report_size(&window, *new_inner_size);
                 }
                 WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
                 WindowEvent::KeyboardInput {
                     input:
                     KeyboardInput {
                         scancode,
+                        state,
                         virtual_keycode,
-                        modifiers,
                         ..
                     },
                     ..
                 } => {
-                    let window_clone = window.clone();
                     if let Some(vir) = virtual_keycode {
                         let key = gl_virtual_to_key(vir);
-                        if ret_vec.contains(&key) {
-                            ret_vec.retain(|x| *x != key);
-                            async_runtime().spawn(async move {
-                                let window = window_clone;
-                                window.key_down.remove(&key).await;
-                            });
-                        } else {
-                            ret_vec.push(key);
-                            async_runtime().spawn(async move {
-                                let window = window_clone;
-                                window.key_down.push(key).await;
-                            });
+                        let is_down = state == ElementState::Pressed;
+                        let repeat = is_down && pressed_keys.contains(&key);
+                        if !is_down {
+                            pressed_keys.retain(|x| *x != key);
+                        } else if !repeat {
+                            pressed_keys.push(key);
                         }
+                        let event = KeyEvent { key, is_down, repeat, modifiers, scancode };
+                        let _ = input.send(SkiaInput::Key(event));
                     }
                     frame += 1;
                 }
+                WindowEvent::ModifiersChanged(state) => {
+                    modifiers = gl_modifier_interpret(state);
+                    let _ = input.send(SkiaInput::Modifiers(modifiers));
+                }
                 WindowEvent::CursorEntered { .. } => {
-                    //let window_clone = window.clone();
+                    cursor_inside = true;
                 }
                 WindowEvent::CursorLeft { .. } => {
-                    let window_clone = window.clone();
-                    async_runtime().spawn(async move {
-                        let window = window_clone;
-                        window.mouse_pos.take().await;
-                    });
+                    cursor_inside = false;
+                    if pressed_buttons.is_empty() {
+                        let _ = input.send(SkiaInput::PointerLeft);
+                    }
                 }
-                WindowEvent::CursorMoved {
-                    position,
-                    modifiers,
-                    ..
-                } => {
+                WindowEvent::CursorMoved { position, .. } => {
                     let new_pos: ScalarPair = (position.x as f32, position.y as f32).into();
                     let new_pos = new_pos.times(1_f32 / skia_get_scale_factor());
                     mouse_pos = new_pos;
-                    let window_clone = window.clone();
-                    async_runtime().spawn(async move {
-                        let window = window_clone;
-                        window.mouse_pos.put(mouse_pos).await;
-                    });
+                    cursor_inside = true;
+                    let _ = input.send(SkiaInput::PointerMoved(mouse_pos, modifiers));
                 }
-                WindowEvent::MouseInput {
-                    state,
-                    button,
-                    modifiers,
-                    ..
-                } => {
+                WindowEvent::MouseInput { state, button, .. } => {
                     let button = gl_mouse_button_interpret(button);
                     match state {
                         ElementState::Pressed => {
-                            let window_clone = window.clone();
-                            async_runtime().spawn(async move {
-                                let window = window_clone;
-                                window.mouse_down.push(button).await;
-                            });
+                            if !pressed_buttons.contains(&button) {
+                                pressed_buttons.push(button);
+                            }
+                            let _ = input.send(SkiaInput::Button(button, true, modifiers));
                         }
                         ElementState::Released => {
-                            let window_clone = window.clone();
-                            async_runtime().spawn(async move {
-                                let window = window_clone;
-                                window.mouse_down.remove(&button).await;
-                            });
+                            pressed_buttons.retain(|x| *x != button);
+                            let _ = input.send(SkiaInput::Button(button, false, modifiers));
+                            // Released outside the window, the pointer leaves it only now
+                            if pressed_buttons.is_empty() && !cursor_inside {
+                                let _ = input.send(SkiaInput::PointerLeft);
+                            }
                         }
                     }
                 }
+                WindowEvent::MouseWheel { delta, phase, .. } => {
+                    let delta = gl_scroll_delta_interpret(delta, skia_get_scale_factor());
+                    let phase = gl_touch_phase_interpret(phase);
+                    let _ = input.send(SkiaInput::Wheel(delta, phase, modifiers));
+                }
                 WindowEvent::Ime(ev) => match ev {
                     Ime::Enabled => {
                         println!("Ime enabled");
@@ -272,16 +350,34 @@ pub fn skia_gl_launch(window: CbWindow, env_id: usize) {
                 {
                     let mut surface = env.surface.borrow_mut();
                     let canvas = surface.canvas();
-                    canvas.clear(Color::WHITE);
                     canvas.reset_matrix();
                     let scale_factor = skia_get_scale_factor();
                     canvas.scale((scale_factor, scale_factor));
-                    canvas.save();
-                    let batch = async_runtime().block_on(async {
-                        window.root.get().await.batch.get_cloned().await
+                    let checkpoint = canvas.save();
+                    let (batch, damage) = async_runtime().block_on(async {
+                        (window.compose_batch().await, window.take_damage().await)
                     });
-                    skia_render_batch(canvas, batch);
-                    canvas.restore();
+                    // Anything but the frame before the last in the back buffer, including
+                    // triple buffering and drivers not preserving it, takes a full redraw
+                    let buffer_age = env.windowed_context.buffer_age();
+                    let clip = match (damage, previous_damage) {
+                        (Some(damage), Some(previous)) if buffer_age == 2 =>
+                            Some(damage.union(previous)),
+                        _ => None,
+                    };
+                    previous_damage = damage;
+                    if let Some(clip) = clip {
+                        // Round outwards so antialiased edges are repainted too
+                        let begin = (clip.origin.x.floor() - 1.0, clip.origin.y.floor() - 1.0);
+                        let end = (clip.end().x.ceil() + 1.0, clip.end().y.ceil() + 1.0);
+                        canvas.clip_rect(
+                            Rect::from_ltrb(begin.0, begin.1, end.0, end.1), None, None);
+                    }
+                    canvas.clear(Color::WHITE);
+                    let mut picture_cache = env.picture_cache.borrow_mut();
+                    skia_render_batch_cached(canvas, &batch, &mut picture_cache);
+                    picture_cache.end_frame();
+                    canvas.restore_to_count(checkpoint);
                     canvas.flush();
                 }
                 env.windowed_context.swap_buffers().unwrap();
@@ -1,5 +1,16 @@
 use std::sync::Arc;
 use crate::caribou::batch::Brush;
 use crate::caribou::math::{Region, Scalar, ScalarPair};
+use crate::caribou::state::Arbitrary;
 use crate::caribou::text::{FontInfo, FontSlant};
 use crate::cb_backend_skia_gl::{skia_create_font, SkiaFont, SkiaFontSlant, SkiaFontWeight, SkiaFontWidth};
+
+pub fn skia_measure_text(font: &Arbitrary, text: &str) -> ScalarPair {
+    let font = match font.get::<SkiaFont>() {
+        None => return ScalarPair::zero(),
+        Some(font) => font,
+    };
+    let (width, _) = font.measure_str(text, None);
+    let (_, metrics) = font.metrics();
+    (width, metrics.descent - metrics.ascent).into()
+}
@@ -2,11 +2,15 @@ use log::info;
 use crate::caribou::batch::{begin_draw, begin_paint, Brush, Colors, Material, Painting, SolidColor, TextAlign, Transform};
 use crate::caribou::gadget::Gadget;
 use crate::caribou::input::{Key, MouseButton};
-use crate::caribou::math::ScalarPair;
+use crate::caribou::math::{Insets, Region, ScalarPair};
 use crate::caribou::state::{Arbitrary, State};
+use crate::cb_backend_skia_gl::text::skia_measure_text;
 
 pub struct Button;
 
+const BUTTON_MIN_DIM: ScalarPair = ScalarPair { x: 64.0, y: 30.0 };
+const BUTTON_PADDING: Insets = Insets { left: 12.0, top: 7.0, right: 12.0, bottom: 7.0 };
+
 impl Button {
     pub async fn create(style: ButtonStyle) -> Gadget {
         let gadget = Gadget::default();
@@ -20,10 +24,20 @@ impl Button {
         gadget.data.set_any(data).await;
 
         // Fill common properties
+        gadget.type_name.set("Button").await;
         gadget.dim.set(ScalarPair::new(100.0, 30.0)).await;
         gadget.accept_focus.set(true).await;
         gadget.lock_focus.set(false).await;
         gadget.accept_text.set(true).await;
+        gadget.min_dim.set(BUTTON_MIN_DIM).await;
+        gadget.padding.set(BUTTON_PADDING).await;
+        gadget.set_measurer(|button, _| Box::pin(async move {
+            let font = button.font.get_cloned().await;
+            let caption = button.data.get_cloned().await
+                .get::<ButtonData>().await
+                .caption.get_cloned().await;
+            skia_measure_text(&font, &caption) + button.padding.get_cloned().await.size()
+        })).await;
 
         // Initial update
         button_batch_update(gadget.clone()).await;
@@ -62,8 +76,32 @@ impl Button {
                 button_batch_update(event.gadget.get().unwrap()).await;
             })).await;
 
+        data.caption.listen(
+            "button_measure_update",
+            |event| Box::pin(async move {
+                event.gadget.get().unwrap().invalidate_measure().await;
+            })).await;
+
         drop(data);
 
+        gadget.font.listen(
+            "button_measure_update",
+            |event| Box::pin(async move {
+                event.gadget.get().unwrap().invalidate_measure().await;
+            })).await;
+
+        gadget.padding.listen(
+            "button_measure_update",
+            |event| Box::pin(async move {
+                event.gadget.get().unwrap().invalidate_measure().await;
+            })).await;
+
+        gadget.padding.listen(
+            "button_batch_update",
+            |event| Box::pin(async move {
+                button_batch_update(event.gadget.get().unwrap()).await;
+            })).await;
+
         // Listen state update
         gadget.mouse_pos.listen_set(
             "button_state_update",
@@ -159,6 +197,7 @@ async fn button_batch_update(button: Gadget) {
     let enabled = gadget.enabled.get_cloned().await;
     let focused = gadget.is_focused().await;
     let dim = gadget.dim.get_cloned().await;
+    let content = gadget.content_region().await;
     let font = gadget.font.get().await;
     let data = gadget
         .data.get().await;
@@ -176,7 +215,7 @@ async fn button_batch_update(button: Gadget) {
                            state))
         .with(|p| style.style_impl
             .draw_caption(p,
-                          dim,
+                          content,
                           enabled,
                           state,
                           caption.clone(),
@@ -218,9 +257,10 @@ pub trait ButtonStyleImpl {
                      dim: ScalarPair,
                      enabled: bool,
                      state: ButtonState) -> Painting;
+    /// Draws the caption within `content`, the button without its padding.
     fn draw_caption(&self,
                     painting: Painting,
-                    dim: ScalarPair,
+                    content: Region,
                     enabled: bool,
                     state: ButtonState,
                     text: String,
@@ -284,7 +324,7 @@ impl ButtonStyleImpl for SimpleButtonStyleImpl {
     }
     fn draw_caption(&self,
                     painting: Painting,
-                    dim: ScalarPair,
+                    content: Region,
                     enabled: bool,
                     state: ButtonState,
                     text: String,
@@ -301,7 +341,7 @@ impl ButtonStyleImpl for SimpleButtonStyleImpl {
         };
         painting
             .text(
-                Transform::from_translate(dim.times(0.5)),
+                Transform::from_translate(content.center()),
                 text, font,
                 TextAlign::Center,
                 Brush::from_fill(filling))
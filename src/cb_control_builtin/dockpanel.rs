@@ -0,0 +1,621 @@
+use std::future::Future;
+use std::pin::Pin;
+use crate::caribou::batch::{begin_draw, begin_paint, Brush, SolidColor, TextAlign, Transform};
+use crate::caribou::gadget::{Gadget, GadgetParent, GadgetRef};
+use crate::caribou::input::{CursorIcon, MouseButton};
+use crate::caribou::layout::{Layout, layout_arrange_on, layout_place, layout_unwatch_child,
+                             layout_watch_child, Orientation};
+use crate::caribou::math::{Region, Scalar, ScalarPair};
+use crate::caribou::measure::Constraints;
+use crate::caribou::state::{Arbitrary, State};
+use crate::cb_control_builtin::splitter::Splitter;
+
+/// Container docking its children to one of its edges in order, each taking a strip of what
+/// the previous ones left, or filling what remains. Docked children are resized by dragging the
+/// splitter on their inner edge, or with the arrow keys while it is focused. Panels made by
+/// [`DockPanel::create_panel`] are docked elsewhere by dragging their header onto one of the
+/// drop targets shown at the edges.
+pub struct DockPanel;
+
+/// Edge a child is docked to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DockSide {
+    Left,
+    Top,
+    Right,
+    Bottom,
+    /// All that remains once the children before are docked. Children after get no room.
+    #[default]
+    Fill,
+}
+
+impl DockSide {
+    /// Axis along which a child docked to this side is sized, none when filling.
+    pub fn orientation(self) -> Option<Orientation> {
+        match self {
+            DockSide::Left | DockSide::Right => Some(Orientation::Horizontal),
+            DockSide::Top | DockSide::Bottom => Some(Orientation::Vertical),
+            DockSide::Fill => None,
+        }
+    }
+
+    /// Whether the side is at the start of its axis (left or top).
+    fn is_start(self) -> bool {
+        matches!(self, DockSide::Left | DockSide::Top)
+    }
+}
+
+/// Thickness of the splitters along the axis of their child.
+pub const DOCK_SPLITTER_THICKNESS: Scalar = 6.0;
+/// Height of the header of panels made by [`DockPanel::create_panel`].
+pub const DOCK_HEADER_HEIGHT: Scalar = 24.0;
+const DOCK_TARGET_SIZE: Scalar = 32.0;
+const DOCK_TARGET_MARGIN: Scalar = 12.0;
+
+const DOCK_SIDE_KEY: &str = "dock.side";
+const DOCK_SIZE_KEY: &str = "dock.size";
+const DOCK_OWNER_KEY: &str = "dock.owner";
+
+impl DockPanel {
+    pub async fn create() -> Gadget {
+        let gadget = Layout::create().await;
+        let overlay = Gadget::default();
+        overlay.type_name.set("DockOverlay").await;
+        overlay.visible.set(false).await;
+
+        // Fill specialized data
+        let data = DockPanelData {
+            items: State::new(gadget.refer(), Vec::new()),
+            drag: State::new(gadget.refer(), None),
+            overlay: overlay.clone(),
+        };
+        gadget.data.set_any(data.clone()).await;
+
+        // Fill common properties
+        gadget.type_name.set("DockPanel").await;
+        gadget.set_measurer(|dock, _| Box::pin(async move {
+            dock_measure(dock).await
+        })).await;
+
+        // Assemble parts
+        Layout::add_child(&gadget, overlay).await;
+
+        // Listen re-layout
+        layout_arrange_on(&gadget.dim, "dock_arrange", dock_arrange_boxed, false).await;
+        layout_arrange_on(&gadget.padding, "dock_arrange", dock_arrange_boxed, true).await;
+
+        // Listen re-docking
+        data.drag.listen(
+            "dock_overlay_update",
+            |event| Box::pin(async move {
+                dock_overlay_update(event.gadget.get().unwrap()).await;
+            })).await;
+
+        gadget.mouse_pos.listen_change(
+            "dock_drag",
+            |event| Box::pin(async move {
+                let dock = event.gadget.get().unwrap();
+                let data = dock_data(&dock).await;
+                let drag = match data.drag.get_cloned().await {
+                    None => return,
+                    Some(drag) => drag,
+                };
+                let content = dock.content_region().await;
+                let target = [DockSide::Left, DockSide::Top, DockSide::Right, DockSide::Bottom]
+                    .into_iter()
+                    .find(|side| dock_target_region(content, *side).contains(event.new_value));
+                data.drag.set_if_changed(Some(DockDrag { target, ..drag })).await;
+            })).await;
+
+        gadget.mouse_pos.listen_unset(
+            "dock_drag",
+            |event| Box::pin(async move {
+                let dock = event.gadget.get().unwrap();
+                dock_data(&dock).await.drag.set_if_changed(None).await;
+            })).await;
+
+        gadget.mouse_down.listen_remove(
+            "dock_drag",
+            |event| Box::pin(async move {
+                if event.old_value != MouseButton::Primary {
+                    return;
+                }
+                let dock = event.gadget.get().unwrap();
+                let data = dock_data(&dock).await;
+                let drag = data.drag.get_cloned().await;
+                data.drag.set_if_changed(None).await;
+                if let Some(DockDrag { panel, target: Some(side) }) = drag {
+                    DockPanel::redock(&dock, &panel, side).await;
+                }
+            })).await;
+
+        gadget
+    }
+
+    /// Docks a child after the ones already docked, with a splitter on its inner edge.
+    pub async fn add_child(dock: &Gadget, child: Gadget, side: DockSide) {
+        let data = dock_data(dock).await;
+        DockPanel::set_dock(&child, side).await;
+        child.values.set(DOCK_OWNER_KEY.to_string(), Arbitrary::new(dock.refer())).await;
+        let splitter = dock_splitter_create(&child).await;
+        Layout::add_child(dock, child.clone()).await;
+        Layout::add_child(dock, splitter.clone()).await;
+        layout_watch_child(dock, &child, dock_arrange_boxed).await;
+        data.items.get_mut().await.push((child, splitter));
+        dock.invalidate_measure().await;
+        dock_arrange(dock.clone()).await;
+    }
+
+    pub async fn remove_child(dock: &Gadget, child: &Gadget) {
+        let data = dock_data(dock).await;
+        let (child, splitter) = {
+            let mut items = data.items.get_mut().await;
+            let index = match items.iter().position(|(item, _)| item == child) {
+                None => return,
+                Some(index) => index,
+            };
+            items.remove(index)
+        };
+        layout_unwatch_child(&child).await;
+        child.values.remove_listener("dock_splitter_orientation").await;
+        child.values.remove(&DOCK_OWNER_KEY.to_string()).await;
+        Layout::remove_child(dock, splitter).await;
+        Layout::remove_child(dock, child).await;
+        dock.invalidate_measure().await;
+        dock_arrange(dock.clone()).await;
+    }
+
+    /// Docks a child again to a side, outside all the others and at its measured size.
+    pub async fn redock(dock: &Gadget, child: &Gadget, side: DockSide) {
+        let data = dock_data(dock).await;
+        {
+            let mut items = data.items.get_mut().await;
+            let index = match items.iter().position(|(item, _)| item == child) {
+                None => return,
+                Some(index) => index,
+            };
+            let item = items.remove(index);
+            items.insert(0, item);
+        }
+        child.values.remove(&DOCK_SIZE_KEY.to_string()).await;
+        DockPanel::set_dock(child, side).await;
+        dock.invalidate_measure().await;
+        dock_arrange(dock.clone()).await;
+    }
+
+    /// Attaches the side a child is docked to, docking it again within its container.
+    pub async fn set_dock(child: &Gadget, side: DockSide) {
+        child.values.set(DOCK_SIDE_KEY.to_string(), Arbitrary::new(side)).await;
+    }
+
+    pub async fn dock(child: &Gadget) -> DockSide {
+        child.values.get(&DOCK_SIDE_KEY.to_string()).await
+            .and_then(|side| side.get::<DockSide>().copied())
+            .unwrap_or_default()
+    }
+
+    /// Attaches the size of a docked child along its axis, as set by its splitter. Without it,
+    /// the child is docked at its measured size.
+    pub async fn set_dock_size(child: &Gadget, size: Scalar) {
+        child.values.set(DOCK_SIZE_KEY.to_string(), Arbitrary::new(size)).await;
+    }
+
+    pub async fn dock_size(child: &Gadget) -> Option<Scalar> {
+        child.values.get(&DOCK_SIZE_KEY.to_string()).await?
+            .get::<Scalar>()
+            .copied()
+    }
+
+    /// Wraps content with a header showing a title, by which it can be dragged to dock it
+    /// elsewhere.
+    pub async fn create_panel(title: &str, content: Gadget) -> Gadget {
+        let gadget = Layout::create().await;
+        let header = dock_header_create(&gadget, title).await;
+
+        // Fill specialized data
+        let data = DockFrameData {
+            header: header.clone(),
+            content: content.clone(),
+        };
+        gadget.data.set_any(data).await;
+
+        // Fill common properties
+        gadget.type_name.set("DockPanelFrame").await;
+        gadget.set_measurer(|frame, constraints| Box::pin(async move {
+            let content = dock_frame_data(&frame).await.content;
+            let header = ScalarPair::new(0.0, DOCK_HEADER_HEIGHT);
+            content.measure(Constraints::loose(constraints.shrink(header).max)).await + header
+        })).await;
+
+        // Assemble parts
+        Layout::add_child(&gadget, header).await;
+        Layout::add_child(&gadget, content.clone()).await;
+        layout_watch_child(&gadget, &content, dock_frame_arrange_boxed).await;
+
+        // Listen re-layout
+        layout_arrange_on(&gadget.dim, "dock_frame_arrange", dock_frame_arrange_boxed, false).await;
+
+        dock_frame_arrange(gadget.clone()).await;
+
+        gadget
+    }
+}
+
+#[derive(Clone)]
+pub struct DockPanelData {
+    /// Docked children in docking order, each with its splitter.
+    items: State<Vec<(Gadget, Gadget)>>,
+    drag: State<Option<DockDrag>>,
+    /// Drop targets shown while a panel is dragged.
+    overlay: Gadget,
+}
+
+pub async fn dock_data(dock: &Gadget) -> DockPanelData {
+    let data = dock.data.get_cloned().await;
+    let data = data.get::<DockPanelData>().await;
+    (*data).clone()
+}
+
+#[derive(Clone, PartialEq)]
+struct DockDrag {
+    panel: Gadget,
+    /// Side of the drop target under the pointer.
+    target: Option<DockSide>,
+}
+
+async fn dock_owner(child: &Gadget) -> Option<Gadget> {
+    child.values.get(&DOCK_OWNER_KEY.to_string()).await?
+        .get::<GadgetRef>()?
+        .get()
+}
+
+/// Size of a docked child along an axis, attached or measured, within its bounds.
+async fn dock_child_size(child: &Gadget, orientation: Orientation, cross: Scalar) -> Scalar {
+    let size = match DockPanel::dock_size(child).await {
+        Some(size) => size,
+        None => orientation.main(child.measure(
+            Constraints::loose(orientation.pair(Scalar::INFINITY, cross))).await),
+    };
+    let own = child.own_constraints().await;
+    size.min(orientation.main(own.max)).max(orientation.main(own.min))
+}
+
+/// Docked children side by side as they are docked, with the filling one in the middle.
+async fn dock_measure(dock: Gadget) -> ScalarPair {
+    let data = dock_data(&dock).await;
+    let mut used = ScalarPair::zero();
+    let mut desired = ScalarPair::zero();
+    for (child, _) in data.items.get_cloned().await.iter() {
+        let measured = child.measure(Constraints::unbounded()).await;
+        match DockPanel::dock(child).await.orientation() {
+            Some(orientation) => {
+                let size = dock_child_size(child, orientation, Scalar::INFINITY).await;
+                let cross = orientation.cross(used) + orientation.cross(measured);
+                desired = orientation.pair(orientation.main(desired),
+                                           orientation.cross(desired).max(cross));
+                used = used + orientation.pair(size + DOCK_SPLITTER_THICKNESS, 0.0);
+            }
+            None => {
+                let filled = used + measured;
+                desired = ScalarPair::new(desired.x.max(filled.x), desired.y.max(filled.y));
+            }
+        }
+    }
+    ScalarPair::new(desired.x.max(used.x), desired.y.max(used.y))
+        + dock.padding.get_cloned().await.size()
+}
+
+fn dock_arrange_boxed(dock: Gadget) -> Pin<Box<dyn Future<Output=()> + Send + Sync>> {
+    Box::pin(dock_arrange(dock))
+}
+
+async fn dock_arrange(dock: Gadget) {
+    let data = dock_data(&dock).await;
+    let content = dock.content_region().await;
+    data.overlay.pos.set_if_changed(content.origin).await;
+    data.overlay.dim.set_if_changed(content.size).await;
+    let mut remaining = content;
+    for (child, splitter) in data.items.get_cloned().await.iter() {
+        let side = DockPanel::dock(child).await;
+        let orientation = match side.orientation() {
+            Some(orientation) => orientation,
+            None => {
+                layout_place(child, remaining).await;
+                splitter.visible.set_if_changed(false).await;
+                remaining = Region::from_origin_size(remaining.origin, ScalarPair::zero());
+                continue;
+            }
+        };
+        let available = orientation.main(remaining.size);
+        let cross = orientation.cross(remaining.size);
+        let size = dock_child_size(child, orientation, cross).await
+            .min((available - DOCK_SPLITTER_THICKNESS).max(0.0));
+        let thickness = DOCK_SPLITTER_THICKNESS.min(available - size);
+        let (child_start, splitter_start, rest_start) = if side.is_start() {
+            (0.0, size, size + thickness)
+        } else {
+            (available - size, available - size - thickness, 0.0)
+        };
+        layout_place(child, Region::from_origin_size(
+            remaining.origin + orientation.pair(child_start, 0.0),
+            orientation.pair(size, cross))).await;
+        splitter.visible.set_if_changed(true).await;
+        splitter.pos.set_if_changed(remaining.origin + orientation.pair(splitter_start, 0.0)).await;
+        splitter.dim.set_if_changed(orientation.pair(thickness, cross)).await;
+        remaining = Region::from_origin_size(
+            remaining.origin + orientation.pair(rest_start, 0.0),
+            orientation.pair(available - size - thickness, cross));
+    }
+}
+
+fn dock_target_region(content: Region, side: DockSide) -> Region {
+    let target = ScalarPair::new(DOCK_TARGET_SIZE, DOCK_TARGET_SIZE);
+    let center = content.center() - ScalarPair::new(DOCK_TARGET_SIZE / 2.0,
+                                                    DOCK_TARGET_SIZE / 2.0);
+    let far = content.origin + content.size - target - ScalarPair::new(DOCK_TARGET_MARGIN,
+                                                                       DOCK_TARGET_MARGIN);
+    let near = content.origin + ScalarPair::new(DOCK_TARGET_MARGIN, DOCK_TARGET_MARGIN);
+    let origin = match side {
+        DockSide::Left => ScalarPair::new(near.x, center.y),
+        DockSide::Top => ScalarPair::new(center.x, near.y),
+        DockSide::Right => ScalarPair::new(far.x, center.y),
+        DockSide::Bottom => ScalarPair::new(center.x, far.y),
+        DockSide::Fill => center,
+    };
+    Region::from_origin_size(origin, target)
+}
+
+/// Where a panel dropped on a target would roughly go, as a preview.
+fn dock_preview_region(content: Region, side: DockSide) -> Region {
+    let quarter = ScalarPair::new(content.size.x / 4.0, content.size.y / 4.0);
+    match side {
+        DockSide::Left => Region::from_origin_size(content.origin, (quarter.x, content.size.y)),
+        DockSide::Top => Region::from_origin_size(content.origin, (content.size.x, quarter.y)),
+        DockSide::Right => Region::from_origin_size(
+            content.origin + ScalarPair::new(content.size.x - quarter.x, 0.0),
+            (quarter.x, content.size.y)),
+        DockSide::Bottom => Region::from_origin_size(
+            content.origin + ScalarPair::new(0.0, content.size.y - quarter.y),
+            (content.size.x, quarter.y)),
+        DockSide::Fill => content,
+    }
+}
+
+/// Shows the drop targets above all children while a panel is dragged.
+async fn dock_overlay_update(dock: Gadget) {
+    let data = dock_data(&dock).await;
+    let overlay = data.overlay.clone();
+    let drag = data.drag.get_cloned().await;
+    let target = match &drag {
+        None => {
+            overlay.visible.set_if_changed(false).await;
+            return;
+        }
+        Some(drag) => drag.target,
+    };
+    if !overlay.visible.get_cloned().await {
+        // Children docked since are above the overlay
+        Layout::remove_child(&dock, overlay.clone()).await;
+        Layout::add_child(&dock, overlay.clone()).await;
+        overlay.visible.set(true).await;
+    }
+    let bounds = Region::from_origin_size(ScalarPair::zero(), overlay.dim.get_cloned().await);
+    let mut painting = begin_paint();
+    if let Some(side) = target {
+        let preview = dock_preview_region(bounds, side);
+        painting = painting
+            .path(Transform::default(),
+                  begin_draw()
+                      .rect(preview.origin, preview.size)
+                      .finish(),
+                  Brush::from_fill(SolidColor::gray_alpha(0.2, 0.25)));
+    }
+    for side in [DockSide::Left, DockSide::Top, DockSide::Right, DockSide::Bottom] {
+        let region = dock_target_region(bounds, side);
+        let fill = if target == Some(side) {
+            SolidColor::gray_alpha(0.2, 0.9)
+        } else {
+            SolidColor::gray_alpha(0.2, 0.6)
+        };
+        painting = painting
+            .path(Transform::default(),
+                  begin_draw()
+                      .rect(region.origin, region.size)
+                      .finish(),
+                  Brush::from_fill(fill));
+    }
+    overlay.batch.set(painting.finish()).await;
+}
+
+async fn dock_splitter_create(child: &Gadget) -> Gadget {
+    let orientation = DockPanel::dock(child).await.orientation()
+        .unwrap_or(Orientation::Horizontal);
+    let (position_ref, move_ref) = (child.refer(), child.refer());
+    let gadget = Splitter::create(orientation, move |_| {
+        let cr = position_ref.clone();
+        Box::pin(async move {
+            match cr.get() {
+                None => 0.0,
+                Some(child) => dock_splitter_position(&child).await,
+            }
+        })
+    }, move |_, position| {
+        let cr = move_ref.clone();
+        Box::pin(async move {
+            if let Some(child) = cr.get() {
+                dock_splitter_move(&child, position).await;
+            }
+        })
+    }).await;
+
+    gadget.type_name.set("DockSplitter").await;
+    gadget.visible.set(false).await;
+
+    // Follow the side of the child
+    let sr = gadget.refer();
+    child.values.listen(
+        "dock_splitter_orientation",
+        move |event| {
+            let sr = sr.clone();
+            Box::pin(async move {
+                let (splitter, child) = match (sr.get(), event.gadget.get()) {
+                    (Some(splitter), Some(child)) => (splitter, child),
+                    _ => return,
+                };
+                if let Some(orientation) = DockPanel::dock(&child).await.orientation() {
+                    Splitter::set_orientation(&splitter, orientation).await;
+                }
+            })
+        }).await;
+
+    gadget
+}
+
+/// Where the splitter of a docked child is along its axis: the size attached to the child, or
+/// its outer size before its splitter first moved, counted backwards for children docked at the
+/// end.
+async fn dock_splitter_position(child: &Gadget) -> Scalar {
+    let side = DockPanel::dock(child).await;
+    let orientation = match side.orientation() {
+        None => return 0.0,
+        Some(orientation) => orientation,
+    };
+    let size = match DockPanel::dock_size(child).await {
+        Some(size) => size,
+        None => orientation.main(child.dim.get_cloned().await)
+            + orientation.main(child.margin.get_cloned().await.size()),
+    };
+    if side.is_start() { size } else { -size }
+}
+
+/// Moves the splitter of a docked child to a position along its axis, measured like
+/// [`dock_splitter_position`], resizing the child within its own bounds and the content of the
+/// dock panel.
+async fn dock_splitter_move(child: &Gadget, position: Scalar) {
+    let side = DockPanel::dock(child).await;
+    let orientation = match side.orientation() {
+        None => return,
+        Some(orientation) => orientation,
+    };
+    let size = if side.is_start() { position } else { -position };
+    let available = match dock_owner(child).await {
+        None => Scalar::INFINITY,
+        Some(dock) => orientation.main(dock.content_region().await.size),
+    };
+    let own = child.own_constraints().await;
+    let size = size
+        .min(available)
+        .min(orientation.main(own.max))
+        .max(orientation.main(own.min))
+        .max(0.0);
+    DockPanel::set_dock_size(child, size).await;
+}
+
+#[derive(Clone)]
+struct DockFrameData {
+    header: Gadget,
+    content: Gadget,
+}
+
+async fn dock_frame_data(frame: &Gadget) -> DockFrameData {
+    let data = frame.data.get_cloned().await;
+    let data = data.get::<DockFrameData>().await;
+    (*data).clone()
+}
+
+fn dock_frame_arrange_boxed(frame: Gadget) -> Pin<Box<dyn Future<Output=()> + Send + Sync>> {
+    Box::pin(dock_frame_arrange(frame))
+}
+
+async fn dock_frame_arrange(frame: Gadget) {
+    let data = dock_frame_data(&frame).await;
+    let region = frame.content_region().await;
+    let header = DOCK_HEADER_HEIGHT.min(region.size.y);
+    data.header.pos.set_if_changed(region.origin).await;
+    data.header.dim.set_if_changed(ScalarPair::new(region.size.x, header)).await;
+    layout_place(&data.content, Region::from_origin_size(
+        region.origin + ScalarPair::new(0.0, header),
+        (region.size.x, region.size.y - header))).await;
+}
+
+#[derive(Clone)]
+struct DockHeaderData {
+    title: State<String>,
+}
+
+async fn dock_header_data(header: &Gadget) -> DockHeaderData {
+    let data = header.data.get_cloned().await;
+    let data = data.get::<DockHeaderData>().await;
+    (*data).clone()
+}
+
+async fn dock_header_create(frame: &Gadget, title: &str) -> Gadget {
+    let gadget = Gadget::default();
+
+    // Fill specialized data
+    let data = DockHeaderData {
+        title: State::new(gadget.refer(), title.to_string()),
+    };
+    gadget.data.set_any(data.clone()).await;
+
+    // Fill common properties
+    gadget.type_name.set("DockPanelHeader").await;
+    gadget.font.set(frame.font.get_cloned().await).await;
+    gadget.cursor.set(Some(CursorIcon::Move)).await;
+
+    // Listen batch update
+    gadget.dim.listen(
+        "dock_header_batch_update",
+        |event| Box::pin(async move {
+            dock_header_batch_update(event.gadget.get().unwrap()).await;
+        })).await;
+
+    data.title.listen(
+        "dock_header_batch_update",
+        |event| Box::pin(async move {
+            dock_header_batch_update(event.gadget.get().unwrap()).await;
+        })).await;
+
+    // Listen dragging, tracked by the dock panel as the pointer leaves the header
+    gadget.mouse_down.listen_add(
+        "dock_header_press",
+        |event| Box::pin(async move {
+            if event.new_value != MouseButton::Primary {
+                return;
+            }
+            let header = event.gadget.get().unwrap();
+            let frame = match header.parent.get_cloned().await {
+                GadgetParent::Gadget(gr) => match gr.get() {
+                    None => return,
+                    Some(frame) => frame,
+                },
+                _ => return,
+            };
+            if let Some(dock) = dock_owner(&frame).await {
+                dock_data(&dock).await.drag
+                    .set(Some(DockDrag { panel: frame, target: None })).await;
+            }
+        })).await;
+
+    dock_header_batch_update(gadget.clone()).await;
+
+    gadget
+}
+
+async fn dock_header_batch_update(header: Gadget) {
+    let data = dock_header_data(&header).await;
+    let dim = header.dim.get_cloned().await;
+    let font = header.font.get_cloned().await;
+    let batch = begin_paint()
+        .path(Transform::default(),
+              begin_draw()
+                  .rect((0.0, 0.0), dim)
+                  .finish(),
+              Brush::from_fill(SolidColor::gray(0.85)))
+        .text(Transform::from_translate(ScalarPair::new(dim.x / 2.0, dim.y / 2.0)),
+              data.title.get_cloned().await, font,
+              TextAlign::Center,
+              Brush::from_fill(SolidColor::gray(0.1)))
+        .finish();
+    header.batch.set(batch).await;
+}
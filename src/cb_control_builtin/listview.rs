@@ -0,0 +1,421 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use crate::caribou::gadget::Gadget;
+use crate::caribou::input::Key;
+use crate::caribou::layout::{Layout, layout_arrange_on, layout_place, Orientation};
+use crate::caribou::math::{Region, Scalar, ScalarPair};
+use crate::caribou::measure::Constraints;
+use crate::caribou::state::State;
+use crate::cb_control_builtin::scrollbar::{SCROLLBAR_THICKNESS, ScrollBar, scrollbar_data, ScrollBarStyle};
+
+/// Vertical list of any number of items, of which only the visible ones plus `overscan` on
+/// either side have a gadget. Row gadgets come from the factory and are bound to an item index
+/// by the binder; rows scrolled out of view are bound to other items instead of being dropped.
+pub struct ListView;
+
+/// Creates an empty row gadget.
+pub type ItemFactory = Arc<dyn Fn() -> Pin<Box<dyn Future<Output=Gadget> + Send + Sync>>
+    + Send + Sync>;
+
+/// Fills a row gadget with the item at an index.
+pub type ItemBinder = Arc<dyn Fn(Gadget, usize) -> Pin<Box<dyn Future<Output=()> + Send + Sync>>
+    + Send + Sync>;
+
+/// Height of the rows of a list.
+#[derive(Clone)]
+pub enum ItemHeight {
+    Fixed(Scalar),
+    /// Height of each item by index, asked for every item whenever the count or this changes.
+    Variable(Arc<dyn Fn(usize) -> Scalar + Send + Sync>),
+}
+
+impl Default for ItemHeight {
+    fn default() -> Self {
+        ItemHeight::Fixed(24.0)
+    }
+}
+
+const LISTVIEW_OVERSCAN: usize = 4;
+
+impl ListView {
+    pub async fn create<F, B>(count: usize, factory: F, binder: B) -> Gadget
+    where
+        F: Fn() -> Pin<Box<dyn Future<Output=Gadget> + Send + Sync>> + Send + Sync + 'static,
+        B: Fn(Gadget, usize) -> Pin<Box<dyn Future<Output=()> + Send + Sync>>
+            + Send + Sync + 'static,
+    {
+        let gadget = Layout::create().await;
+        let presenter = Layout::create().await;
+        presenter.type_name.set("ListPresenter").await;
+        let bar = ScrollBar::create(Orientation::Vertical, ScrollBarStyle::default()).await;
+
+        // Fill specialized data
+        let data = ListViewData {
+            count: State::new(gadget.refer(), count),
+            item_height: State::new(gadget.refer(), ItemHeight::default()),
+            overscan: State::new(gadget.refer(), LISTVIEW_OVERSCAN),
+            offset: State::new(gadget.refer(), 0.0),
+            factory: Arc::new(factory),
+            binder: Arc::new(binder),
+            presenter: presenter.clone(),
+            bar: bar.clone(),
+            rows: State::new(gadget.refer(), Vec::new()),
+            tops: State::new(gadget.refer(), None),
+        };
+        gadget.data.set_any(data.clone()).await;
+
+        // Fill common properties
+        gadget.type_name.set("ListView").await;
+        gadget.accept_focus.set(true).await;
+        gadget.set_measurer(|list, _| Box::pin(async move {
+            listview_measure(list).await
+        })).await;
+        gadget.set_scroll_handler(|list, event| Box::pin(async move {
+            if event.modifiers.has_control() {
+                // Left to zooming
+                return false;
+            }
+            ListView::scroll_by(&list, event.pixels().y).await
+        })).await;
+
+        // Assemble parts
+        Layout::add_child(&gadget, presenter).await;
+        Layout::add_child(&gadget, bar.clone()).await;
+
+        // Listen re-layout
+        layout_arrange_on(&gadget.dim, "listview_arrange", listview_arrange_boxed, false).await;
+        layout_arrange_on(&gadget.padding, "listview_arrange", listview_arrange_boxed, true).await;
+
+        data.count.listen(
+            "listview_arrange",
+            |event| Box::pin(async move {
+                let list = event.gadget.get().unwrap();
+                listview_data(&list).await.tops.set(None).await;
+                list.invalidate_measure().await;
+                listview_arrange(list).await;
+            })).await;
+
+        data.item_height.listen(
+            "listview_arrange",
+            |event| Box::pin(async move {
+                let list = event.gadget.get().unwrap();
+                listview_data(&list).await.tops.set(None).await;
+                list.invalidate_measure().await;
+                listview_arrange(list).await;
+            })).await;
+
+        layout_arrange_on(&data.overscan, "listview_arrange", listview_arrange_boxed, false).await;
+        layout_arrange_on(&data.offset, "listview_arrange", listview_arrange_boxed, false).await;
+
+        // Listen scroll bar
+        let lr = gadget.refer();
+        scrollbar_data(&bar).await.value.listen(
+            "listview_bar",
+            move |event| {
+                let lr = lr.clone();
+                Box::pin(async move {
+                    if let Some(list) = lr.get() {
+                        let offset = event.state.get_cloned().await;
+                        ListView::scroll_to(&list, offset).await;
+                    }
+                })
+            }).await;
+
+        // Listen keyboard
+        gadget.key_down.listen_add(
+            "listview_key",
+            |event| Box::pin(async move {
+                let list = event.gadget.get().unwrap();
+                let page = list.content_region().await.size.y;
+                match event.new_value {
+                    Key::PageUp => ListView::scroll_by(&list, -page).await,
+                    Key::PageDown => ListView::scroll_by(&list, page).await,
+                    Key::Home => ListView::scroll_to(&list, 0.0).await,
+                    Key::End => ListView::scroll_to(&list, Scalar::INFINITY).await,
+                    _ => false,
+                };
+            })).await;
+
+        listview_arrange(gadget.clone()).await;
+
+        gadget
+    }
+
+    /// Scrolls to an offset from the top of the first item, kept within the items. Returns
+    /// whether it moved.
+    pub async fn scroll_to(list: &Gadget, offset: Scalar) -> bool {
+        let data = listview_data(list).await;
+        let maximum = listview_maximum(list, &data).await;
+        data.offset.set_if_changed(offset.clamp(0.0, maximum)).await
+    }
+
+    pub async fn scroll_by(list: &Gadget, delta: Scalar) -> bool {
+        let offset = listview_data(list).await.offset.get_cloned().await;
+        ListView::scroll_to(list, offset + delta).await
+    }
+
+    /// Scrolls as little as possible to show the whole item at an index.
+    pub async fn scroll_into_view(list: &Gadget, index: usize) -> bool {
+        let data = listview_data(list).await;
+        let tops = listview_tops(&data).await;
+        if index >= tops.count() {
+            return false;
+        }
+        let offset = data.offset.get_cloned().await;
+        let page = listview_viewport(list, tops.total()).await.y;
+        let top = tops.top(index);
+        let bottom = tops.top(index + 1);
+        if top < offset {
+            ListView::scroll_to(list, top).await
+        } else if bottom > offset + page {
+            ListView::scroll_to(list, bottom - page).await
+        } else {
+            false
+        }
+    }
+
+    /// Binds every row again, for when the items changed without their count changing.
+    pub async fn refresh(list: &Gadget) {
+        let data = listview_data(list).await;
+        let rows = data.rows.get().await;
+        for row in rows.iter() {
+            if let Some(index) = row.index {
+                (data.binder)(row.gadget.clone(), index).await;
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct ListViewData {
+    pub count: State<usize>,
+    pub item_height: State<ItemHeight>,
+    /// Rows realized beyond each edge of the viewport.
+    pub overscan: State<usize>,
+    /// Distance from the top of the first item to the top of the viewport.
+    pub offset: State<Scalar>,
+    factory: ItemFactory,
+    binder: ItemBinder,
+    presenter: Gadget,
+    bar: Gadget,
+    /// Realized row gadgets, whose lock also serializes arranging.
+    rows: State<Vec<ListRow>>,
+    /// Top of every item for variable heights, built when first needed.
+    tops: State<Option<Arc<Vec<Scalar>>>>,
+}
+
+pub async fn listview_data(list: &Gadget) -> ListViewData {
+    let data = list.data.get_cloned().await;
+    let data = data.get::<ListViewData>().await;
+    (*data).clone()
+}
+
+#[derive(Clone)]
+struct ListRow {
+    gadget: Gadget,
+    /// Bound item, `None` for a spare row.
+    index: Option<usize>,
+}
+
+/// Where the items are along the list.
+enum ListTops {
+    Fixed { count: usize, height: Scalar },
+    /// The top of every item followed by the bottom of the last one.
+    Variable(Arc<Vec<Scalar>>),
+}
+
+impl ListTops {
+    fn count(&self) -> usize {
+        match self {
+            ListTops::Fixed { count, .. } => *count,
+            ListTops::Variable(tops) => tops.len() - 1,
+        }
+    }
+
+    /// Top of an item, or the bottom of the last one for the count.
+    fn top(&self, index: usize) -> Scalar {
+        match self {
+            ListTops::Fixed { height, .. } => index as Scalar * height,
+            ListTops::Variable(tops) => tops[index],
+        }
+    }
+
+    fn total(&self) -> Scalar {
+        self.top(self.count())
+    }
+
+    /// The item at a distance from the top, clamped to the existing ones.
+    fn index_at(&self, offset: Scalar) -> usize {
+        let last = self.count().saturating_sub(1);
+        match self {
+            ListTops::Fixed { height, .. } if *height > 0.0 =>
+                ((offset / height).max(0.0) as usize).min(last),
+            ListTops::Fixed { .. } => 0,
+            ListTops::Variable(tops) =>
+                tops.partition_point(|top| *top <= offset).saturating_sub(1).min(last),
+        }
+    }
+}
+
+async fn listview_tops(data: &ListViewData) -> ListTops {
+    let count = data.count.get_cloned().await;
+    let heights = match data.item_height.get_cloned().await {
+        ItemHeight::Fixed(height) => return ListTops::Fixed { count, height },
+        ItemHeight::Variable(heights) => heights,
+    };
+    let mut cached = data.tops.get_mut().await;
+    if let Some(tops) = cached.as_ref() {
+        if tops.len() == count + 1 {
+            return ListTops::Variable(tops.clone());
+        }
+    }
+    let mut tops = Vec::with_capacity(count + 1);
+    let mut top = 0.0;
+    tops.push(top);
+    for index in 0..count {
+        top += heights(index).max(0.0);
+        tops.push(top);
+    }
+    let tops = Arc::new(tops);
+    *cached = Some(tops.clone());
+    ListTops::Variable(tops)
+}
+
+/// Size left for rows, less the scroll bar if the items do not fit.
+async fn listview_viewport(list: &Gadget, total: Scalar) -> ScalarPair {
+    let region = list.content_region().await;
+    if total > region.size.y {
+        ScalarPair::new((region.size.x - SCROLLBAR_THICKNESS).max(0.0), region.size.y)
+    } else {
+        region.size
+    }
+}
+
+async fn listview_maximum(list: &Gadget, data: &ListViewData) -> Scalar {
+    let total = listview_tops(data).await.total();
+    (total - listview_viewport(list, total).await.y).max(0.0)
+}
+
+/// All items on the height axis, the widest realized row on the other.
+async fn listview_measure(list: Gadget) -> ScalarPair {
+    let data = listview_data(&list).await;
+    let total = listview_tops(&data).await.total();
+    let rows = data.rows.get().await.clone();
+    let mut width: Scalar = 0.0;
+    for row in rows.iter().filter(|row| row.index.is_some()) {
+        width = width.max(row.gadget.measure(Constraints::unbounded()).await.x);
+    }
+    ScalarPair::new(width, total) + list.padding.get_cloned().await.size()
+}
+
+fn listview_arrange_boxed(list: Gadget) -> Pin<Box<dyn Future<Output=()> + Send + Sync>> {
+    Box::pin(listview_arrange(list))
+}
+
+async fn listview_arrange(list: Gadget) {
+    let data = listview_data(&list).await;
+    let mut rows = data.rows.get_mut().await;
+    let tops = listview_tops(&data).await;
+    let total = tops.total();
+    let region = list.content_region().await;
+    let viewport = listview_viewport(&list, total).await;
+    let maximum = (total - viewport.y).max(0.0);
+    let offset = data.offset.get_cloned().await;
+    if offset > maximum {
+        drop(rows);
+        // Arranged again by the offset listener
+        data.offset.set(maximum).await;
+        return;
+    }
+
+    data.presenter.pos.set_if_changed(region.origin).await;
+    data.presenter.dim.set_if_changed(viewport).await;
+    let bar_data = scrollbar_data(&data.bar).await;
+    bar_data.maximum.set_if_changed(maximum).await;
+    bar_data.viewport.set_if_changed(viewport.y).await;
+    bar_data.value.set_if_changed(offset).await;
+    data.bar.pos.set_if_changed(region.origin + ScalarPair::new(viewport.x, 0.0)).await;
+    data.bar.dim.set_if_changed(if maximum > 0.0 {
+        ScalarPair::new(SCROLLBAR_THICKNESS, viewport.y)
+    } else {
+        ScalarPair::zero()
+    }).await;
+
+    // Items to realize
+    let count = tops.count();
+    let overscan = data.overscan.get_cloned().await;
+    let range = if count == 0 {
+        0..0
+    } else {
+        let first = tops.index_at(offset).saturating_sub(overscan);
+        let last = (tops.index_at(offset + viewport.y) + overscan).min(count - 1);
+        first..last + 1
+    };
+
+    // Rows bound outside of it become spare
+    for row in rows.iter_mut() {
+        if row.index.is_some_and(|index| !range.contains(&index)) {
+            row.index = None;
+        }
+    }
+    for index in range.clone() {
+        if rows.iter().any(|row| row.index == Some(index)) {
+            continue;
+        }
+        let position = match rows.iter().position(|row| row.index.is_none()) {
+            Some(position) => position,
+            None => {
+                let gadget = (data.factory)().await;
+                Layout::add_child(&data.presenter, gadget.clone()).await;
+                rows.push(ListRow { gadget, index: None });
+                rows.len() - 1
+            }
+        };
+        rows[position].index = Some(index);
+        (data.binder)(rows[position].gadget.clone(), index).await;
+    }
+
+    for row in rows.iter() {
+        match row.index {
+            Some(index) => {
+                let top = tops.top(index) - offset;
+                let height = tops.top(index + 1) - tops.top(index);
+                layout_place(&row.gadget, Region::from_origin_size(
+                    (0.0, top), (viewport.x, height))).await;
+            }
+            // Parked below the viewport, where they are clipped and cannot be hit
+            None => {
+                row.gadget.pos.set_if_changed(ScalarPair::new(0.0, viewport.y)).await;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn index_at_fixed_heights() {
+        let tops = ListTops::Fixed { count: 10, height: 20.0 };
+        assert_eq!(tops.index_at(0.0), 0);
+        assert_eq!(tops.index_at(39.9), 1);
+        assert_eq!(tops.index_at(40.0), 2);
+        assert_eq!(tops.index_at(-5.0), 0);
+        assert_eq!(tops.index_at(1000.0), 9);
+        assert_eq!(ListTops::Fixed { count: 10, height: 0.0 }.index_at(50.0), 0);
+        assert_eq!(ListTops::Fixed { count: 0, height: 20.0 }.index_at(50.0), 0);
+    }
+
+    #[test]
+    fn index_at_variable_heights() {
+        let tops = ListTops::Variable(Arc::new(vec![0.0, 10.0, 30.0, 60.0]));
+        assert_eq!(tops.index_at(0.0), 0);
+        assert_eq!(tops.index_at(9.9), 0);
+        assert_eq!(tops.index_at(10.0), 1);
+        assert_eq!(tops.index_at(59.0), 2);
+        assert_eq!(tops.index_at(60.0), 2);
+        assert_eq!(tops.index_at(-1.0), 0);
+    }
+}
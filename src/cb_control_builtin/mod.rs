@@ -1,4 +1,11 @@
 pub mod button;
 pub mod textbox;
 pub mod button2;
-pub mod textbox2;
\ No newline at end of file
+pub mod textbox2;
+pub mod scrollbar;
+pub mod scrollviewer;
+pub mod listview;
+pub mod splitter;
+pub mod splitpane;
+pub mod tabview;
+pub mod dockpanel;
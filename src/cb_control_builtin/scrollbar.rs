@@ -0,0 +1,329 @@
+use crate::caribou::batch::{begin_draw, begin_paint, Brush, Material, Painting, SolidColor, Transform};
+use crate::caribou::gadget::Gadget;
+use crate::caribou::input::MouseButton;
+use crate::caribou::layout::Orientation;
+use crate::caribou::math::{Region, Scalar, ScalarPair};
+use crate::caribou::state::State;
+
+/// Bar showing which part of some content is visible, dragged by its thumb or paged by clicking
+/// the track. `value` goes from zero to `maximum`, where the last `viewport` of the content is
+/// visible.
+pub struct ScrollBar;
+
+/// Thickness of scroll bars across their orientation.
+pub const SCROLLBAR_THICKNESS: Scalar = 12.0;
+const SCROLLBAR_MIN_THUMB: Scalar = 16.0;
+
+impl ScrollBar {
+    pub async fn create(orientation: Orientation, style: ScrollBarStyle) -> Gadget {
+        let gadget = Gadget::default();
+
+        // Fill specialized data
+        let data = ScrollBarData {
+            style: State::new(gadget.refer(), style),
+            orientation: State::new(gadget.refer(), orientation),
+            value: State::new(gadget.refer(), 0.0),
+            maximum: State::new(gadget.refer(), 0.0),
+            viewport: State::new(gadget.refer(), 0.0),
+            state: State::new(gadget.refer(), ScrollBarState::Normal),
+            drag: State::new(gadget.refer(), None),
+        };
+        gadget.data.set_any(data.clone()).await;
+
+        // Fill common properties
+        gadget.type_name.set("ScrollBar").await;
+        gadget.dim.set(orientation.pair(100.0, SCROLLBAR_THICKNESS)).await;
+
+        // Initial update
+        scrollbar_batch_update(gadget.clone()).await;
+
+        // Listen batch update
+        gadget.dim.listen(
+            "scrollbar_batch_update",
+            |event| Box::pin(async move {
+                scrollbar_batch_update(event.gadget.get().unwrap()).await;
+            })).await;
+
+        gadget.enabled.listen(
+            "scrollbar_batch_update",
+            |event| Box::pin(async move {
+                scrollbar_batch_update(event.gadget.get().unwrap()).await;
+            })).await;
+
+        data.orientation.listen(
+            "scrollbar_batch_update",
+            |event| Box::pin(async move {
+                scrollbar_batch_update(event.gadget.get().unwrap()).await;
+            })).await;
+
+        data.value.listen(
+            "scrollbar_batch_update",
+            |event| Box::pin(async move {
+                scrollbar_batch_update(event.gadget.get().unwrap()).await;
+            })).await;
+
+        data.maximum.listen(
+            "scrollbar_batch_update",
+            |event| Box::pin(async move {
+                scrollbar_batch_update(event.gadget.get().unwrap()).await;
+            })).await;
+
+        data.viewport.listen(
+            "scrollbar_batch_update",
+            |event| Box::pin(async move {
+                scrollbar_batch_update(event.gadget.get().unwrap()).await;
+            })).await;
+
+        data.state.listen(
+            "scrollbar_batch_update",
+            |event| Box::pin(async move {
+                scrollbar_batch_update(event.gadget.get().unwrap()).await;
+            })).await;
+
+        // Listen state update
+        gadget.mouse_pos.listen_set(
+            "scrollbar_state_update",
+            |event| Box::pin(async move {
+                scrollbar_data(&event.gadget.get().unwrap()).await
+                    .state.set_if_changed(ScrollBarState::Hover).await;
+            })).await;
+
+        gadget.mouse_pos.listen_change(
+            "scrollbar_drag",
+            |event| Box::pin(async move {
+                let scrollbar = event.gadget.get().unwrap();
+                let data = scrollbar_data(&scrollbar).await;
+                let (start, start_value) = match data.drag.get_cloned().await {
+                    None => return,
+                    Some(drag) => drag,
+                };
+                let orientation = data.orientation.get_cloned().await;
+                let track = scrollbar.content_region().await;
+                let thumb = scrollbar_thumb(&scrollbar, &data).await;
+                let movable = orientation.main(track.size) - orientation.main(thumb.size);
+                if movable <= 0.0 {
+                    return;
+                }
+                let maximum = data.maximum.get_cloned().await;
+                let moved = orientation.main(event.new_value) - start;
+                let value = (start_value + moved * maximum / movable).clamp(0.0, maximum);
+                data.value.set_if_changed(value).await;
+            })).await;
+
+        gadget.mouse_pos.listen_unset(
+            "scrollbar_state_update",
+            |event| Box::pin(async move {
+                let data = scrollbar_data(&event.gadget.get().unwrap()).await;
+                data.drag.set(None).await;
+                data.state.set_if_changed(ScrollBarState::Normal).await;
+            })).await;
+
+        gadget.mouse_down.listen_add(
+            "scrollbar_press",
+            |event| Box::pin(async move {
+                if event.new_value != MouseButton::Primary {
+                    return;
+                }
+                let scrollbar = event.gadget.get().unwrap();
+                if !scrollbar.enabled.get_cloned().await {
+                    return;
+                }
+                let pos = match scrollbar.mouse_pos.get().await {
+                    None => return,
+                    Some(pos) => pos,
+                };
+                let data = scrollbar_data(&scrollbar).await;
+                let orientation = data.orientation.get_cloned().await;
+                let thumb = scrollbar_thumb(&scrollbar, &data).await;
+                let value = data.value.get_cloned().await;
+                if thumb.contains(pos) {
+                    data.drag.set(Some((orientation.main(pos), value))).await;
+                    data.state.set_if_changed(ScrollBarState::Pressed).await;
+                    return;
+                }
+                // Clicking the track pages towards the pointer
+                let page = data.viewport.get_cloned().await;
+                let maximum = data.maximum.get_cloned().await;
+                let value = if orientation.main(pos) < orientation.main(thumb.origin) {
+                    value - page
+                } else {
+                    value + page
+                };
+                data.value.set_if_changed(value.clamp(0.0, maximum.max(0.0))).await;
+            })).await;
+
+        gadget.mouse_down.listen_remove(
+            "scrollbar_press",
+            |event| Box::pin(async move {
+                if event.old_value != MouseButton::Primary {
+                    return;
+                }
+                let scrollbar = event.gadget.get().unwrap();
+                let data = scrollbar_data(&scrollbar).await;
+                data.drag.set(None).await;
+                if scrollbar.mouse_pos.get().await.is_some() {
+                    data.state.set_if_changed(ScrollBarState::Hover).await;
+                }
+            })).await;
+
+        gadget
+    }
+}
+
+#[derive(Clone)]
+pub struct ScrollBarData {
+    pub style: State<ScrollBarStyle>,
+    pub orientation: State<Orientation>,
+    pub value: State<Scalar>,
+    pub maximum: State<Scalar>,
+    /// Length of the visible part, in the same unit as `value`.
+    pub viewport: State<Scalar>,
+    state: State<ScrollBarState>,
+    /// Pointer position along the bar and value when the thumb was grabbed.
+    drag: State<Option<(Scalar, Scalar)>>,
+}
+
+pub async fn scrollbar_data(scrollbar: &Gadget) -> ScrollBarData {
+    let data = scrollbar.data.get_cloned().await;
+    let data = data.get::<ScrollBarData>().await;
+    (*data).clone()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScrollBarState {
+    Normal,
+    Hover,
+    Pressed,
+}
+
+/// Where the thumb is within the track, as long as the part of the content it stands for.
+async fn scrollbar_thumb(scrollbar: &Gadget, data: &ScrollBarData) -> Region {
+    let orientation = data.orientation.get_cloned().await;
+    let value = data.value.get_cloned().await;
+    let maximum = data.maximum.get_cloned().await.max(0.0);
+    let viewport = data.viewport.get_cloned().await.max(0.0);
+    let track = scrollbar.content_region().await;
+    let length = orientation.main(track.size);
+    let total = maximum + viewport;
+    let thumb = if total > 0.0 {
+        (length * viewport / total).max(SCROLLBAR_MIN_THUMB).min(length)
+    } else {
+        length
+    };
+    let offset = if maximum > 0.0 {
+        (length - thumb) * value.clamp(0.0, maximum) / maximum
+    } else {
+        0.0
+    };
+    Region::from_origin_size(track.origin + orientation.pair(offset, 0.0),
+                             orientation.pair(thumb, orientation.cross(track.size)))
+}
+
+async fn scrollbar_batch_update(scrollbar: Gadget) {
+    let data = scrollbar_data(&scrollbar).await;
+    let enabled = scrollbar.enabled.get_cloned().await;
+    let dim = scrollbar.dim.get_cloned().await;
+    let thumb = scrollbar_thumb(&scrollbar, &data).await;
+    let state = data.state.get_cloned().await;
+    let style = data.style.get().await;
+    let batch = begin_paint()
+        .with(|p| style.style_impl
+            .draw_track(p,
+                        dim,
+                        enabled))
+        .with(|p| style.style_impl
+            .draw_thumb(p,
+                        thumb,
+                        enabled,
+                        state))
+        .finish();
+    drop(style);
+    scrollbar.batch.set(batch).await;
+}
+
+pub struct ScrollBarStyle {
+    style_impl: Box<dyn ScrollBarStyleImpl + Send + Sync>,
+}
+
+impl ScrollBarStyle {
+    pub fn from_impl(style_impl: impl ScrollBarStyleImpl + Send + Sync + 'static) -> Self {
+        Self { style_impl: Box::new(style_impl) }
+    }
+}
+
+impl Default for ScrollBarStyle {
+    fn default() -> Self {
+        Self::from_impl(SimpleScrollBarStyleImpl::default())
+    }
+}
+
+pub trait ScrollBarStyleImpl {
+    fn draw_track(&self,
+                  painting: Painting,
+                  dim: ScalarPair,
+                  enabled: bool) -> Painting;
+    fn draw_thumb(&self,
+                  painting: Painting,
+                  thumb: Region,
+                  enabled: bool,
+                  state: ScrollBarState) -> Painting;
+}
+
+pub struct SimpleScrollBarStyleImpl {
+    pub bg_track: Material,
+    pub fg_normal: Material,
+    pub fg_hover: Material,
+    pub fg_pressed: Material,
+    pub fg_disabled: Material,
+}
+
+impl Default for SimpleScrollBarStyleImpl {
+    fn default() -> Self {
+        Self {
+            bg_track: SolidColor::gray(0.96).into(),
+            fg_normal: SolidColor::gray(0.75).into(),
+            fg_hover: SolidColor::gray(0.65).into(),
+            fg_pressed: SolidColor::gray(0.5).into(),
+            fg_disabled: SolidColor::gray(0.88).into(),
+        }
+    }
+}
+
+impl ScrollBarStyleImpl for SimpleScrollBarStyleImpl {
+    fn draw_track(&self,
+                  painting: Painting,
+                  dim: ScalarPair,
+                  _enabled: bool
+    ) -> Painting {
+        painting
+            .path(
+                Transform::default(),
+                begin_draw()
+                    .rect((0.0, 0.0), dim)
+                    .finish(),
+                Brush::from_fill(self.bg_track))
+    }
+    fn draw_thumb(&self,
+                  painting: Painting,
+                  thumb: Region,
+                  enabled: bool,
+                  state: ScrollBarState
+    ) -> Painting {
+        let filling = if enabled {
+            match state {
+                ScrollBarState::Normal => self.fg_normal,
+                ScrollBarState::Hover => self.fg_hover,
+                ScrollBarState::Pressed => self.fg_pressed,
+            }
+        } else {
+            self.fg_disabled
+        };
+        painting
+            .path(
+                Transform::default(),
+                begin_draw()
+                    .rect(thumb.origin + (2.0, 2.0).into(), thumb.size - (4.0, 4.0).into())
+                    .finish(),
+                Brush::from_fill(filling))
+    }
+}
@@ -0,0 +1,265 @@
+use std::future::Future;
+use std::pin::Pin;
+use crate::caribou::gadget::Gadget;
+use crate::caribou::input::Key;
+use crate::caribou::layout::{Layout, layout_arrange_on, layout_place, layout_watch_child,
+                             Orientation};
+use crate::caribou::math::{Region, Scalar, ScalarPair};
+use crate::caribou::measure::Constraints;
+use crate::caribou::state::State;
+use crate::cb_control_builtin::scrollbar::{SCROLLBAR_THICKNESS, ScrollBar, scrollbar_data, ScrollBarStyle};
+
+/// Container showing a part of content larger than itself, scrolled by its scroll bars, the
+/// mouse wheel or touchpad, and PageUp/PageDown/Home/End while focused. The content is clipped
+/// to the viewport and moved by `offset`; pointer positions follow it.
+pub struct ScrollViewer;
+
+/// When a scroll viewer shows a scroll bar along one axis.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ScrollBarVisibility {
+    /// Only if the content does not fit.
+    #[default]
+    Auto,
+    Always,
+    /// Never, though the content can still be scrolled.
+    Hidden,
+    /// Never, and the content is fit into the viewport instead of being scrolled.
+    Disabled,
+}
+
+impl ScrollViewer {
+    pub async fn create(content: Gadget) -> Gadget {
+        let gadget = Layout::create().await;
+        let presenter = Layout::create().await;
+        presenter.type_name.set("ScrollPresenter").await;
+        let horizontal_bar = ScrollBar::create(
+            Orientation::Horizontal, ScrollBarStyle::default()).await;
+        let vertical_bar = ScrollBar::create(
+            Orientation::Vertical, ScrollBarStyle::default()).await;
+
+        // Fill specialized data
+        let data = ScrollViewerData {
+            content: content.clone(),
+            offset: State::new(gadget.refer(), ScalarPair::zero()),
+            extent: State::new(gadget.refer(), ScalarPair::zero()),
+            viewport: State::new(gadget.refer(), ScalarPair::zero()),
+            horizontal: State::new(gadget.refer(), ScrollBarVisibility::Auto),
+            vertical: State::new(gadget.refer(), ScrollBarVisibility::Auto),
+            presenter: presenter.clone(),
+            horizontal_bar: horizontal_bar.clone(),
+            vertical_bar: vertical_bar.clone(),
+        };
+        gadget.data.set_any(data.clone()).await;
+
+        // Fill common properties
+        gadget.type_name.set("ScrollViewer").await;
+        gadget.accept_focus.set(true).await;
+        gadget.set_measurer(|viewer, constraints| Box::pin(async move {
+            let content = scrollviewer_data(&viewer).await.content;
+            let padding = viewer.padding.get_cloned().await.size();
+            let constraints = Constraints::loose(constraints.shrink(padding).max);
+            content.measure(constraints).await + padding
+        })).await;
+        gadget.set_scroll_handler(|viewer, event| Box::pin(async move {
+            if event.modifiers.has_control() {
+                // Left to zooming
+                return false;
+            }
+            ScrollViewer::scroll_by(&viewer, event.pixels()).await
+        })).await;
+
+        // Assemble parts
+        Layout::add_child(&presenter, content.clone()).await;
+        Layout::add_child(&gadget, presenter).await;
+        Layout::add_child(&gadget, horizontal_bar.clone()).await;
+        Layout::add_child(&gadget, vertical_bar.clone()).await;
+        layout_watch_child(&gadget, &content, scrollviewer_arrange_boxed).await;
+
+        // Listen re-layout
+        layout_arrange_on(&gadget.dim, "scrollviewer_arrange",
+                          scrollviewer_arrange_boxed, false).await;
+        layout_arrange_on(&gadget.padding, "scrollviewer_arrange",
+                          scrollviewer_arrange_boxed, true).await;
+        layout_arrange_on(&data.offset, "scrollviewer_arrange",
+                          scrollviewer_arrange_boxed, false).await;
+        layout_arrange_on(&data.horizontal, "scrollviewer_arrange",
+                          scrollviewer_arrange_boxed, false).await;
+        layout_arrange_on(&data.vertical, "scrollviewer_arrange",
+                          scrollviewer_arrange_boxed, false).await;
+
+        // Listen scroll bars
+        let vr = gadget.refer();
+        scrollbar_data(&horizontal_bar).await.value.listen(
+            "scrollviewer_bar",
+            move |event| {
+                let vr = vr.clone();
+                Box::pin(async move {
+                    let viewer = match vr.get() {
+                        None => return,
+                        Some(viewer) => viewer,
+                    };
+                    let x = event.state.get_cloned().await;
+                    let offset = scrollviewer_data(&viewer).await.offset.get_cloned().await;
+                    ScrollViewer::scroll_to(&viewer, ScalarPair::new(x, offset.y)).await;
+                })
+            }).await;
+
+        let vr = gadget.refer();
+        scrollbar_data(&vertical_bar).await.value.listen(
+            "scrollviewer_bar",
+            move |event| {
+                let vr = vr.clone();
+                Box::pin(async move {
+                    let viewer = match vr.get() {
+                        None => return,
+                        Some(viewer) => viewer,
+                    };
+                    let y = event.state.get_cloned().await;
+                    let offset = scrollviewer_data(&viewer).await.offset.get_cloned().await;
+                    ScrollViewer::scroll_to(&viewer, ScalarPair::new(offset.x, y)).await;
+                })
+            }).await;
+
+        // Listen keyboard
+        gadget.key_down.listen_add(
+            "scrollviewer_key",
+            |event| Box::pin(async move {
+                let viewer = event.gadget.get().unwrap();
+                let data = scrollviewer_data(&viewer).await;
+                let offset = data.offset.get_cloned().await;
+                let page = data.viewport.get_cloned().await.y;
+                let end = scrollviewer_maximum(&data).await.y;
+                match event.new_value {
+                    Key::PageUp => ScrollViewer::scroll_by(&viewer, (0.0, -page)).await,
+                    Key::PageDown => ScrollViewer::scroll_by(&viewer, (0.0, page)).await,
+                    Key::Home => ScrollViewer::scroll_to(&viewer, (offset.x, 0.0)).await,
+                    Key::End => ScrollViewer::scroll_to(&viewer, (offset.x, end)).await,
+                    _ => false,
+                };
+            })).await;
+
+        scrollviewer_arrange(gadget.clone()).await;
+
+        gadget
+    }
+
+    /// Scrolls to an offset, kept within the content. Returns whether it moved.
+    pub async fn scroll_to<S: Into<ScalarPair>>(viewer: &Gadget, offset: S) -> bool {
+        let offset = offset.into();
+        let data = scrollviewer_data(viewer).await;
+        let maximum = scrollviewer_maximum(&data).await;
+        let offset = ScalarPair::new(offset.x.clamp(0.0, maximum.x),
+                                     offset.y.clamp(0.0, maximum.y));
+        data.offset.set_if_changed(offset).await
+    }
+
+    /// Scrolls by a distance, kept within the content. Returns whether it moved, which is not
+    /// the case at the end of the content.
+    pub async fn scroll_by<S: Into<ScalarPair>>(viewer: &Gadget, delta: S) -> bool {
+        let offset = scrollviewer_data(viewer).await.offset.get_cloned().await;
+        ScrollViewer::scroll_to(viewer, offset + delta.into()).await
+    }
+}
+
+#[derive(Clone)]
+pub struct ScrollViewerData {
+    pub content: Gadget,
+    /// Top left of the content shown at the top left of the viewport.
+    pub offset: State<ScalarPair>,
+    /// Size of the content, at least that of the viewport.
+    pub extent: State<ScalarPair>,
+    /// Size of the visible part of the content.
+    pub viewport: State<ScalarPair>,
+    pub horizontal: State<ScrollBarVisibility>,
+    pub vertical: State<ScrollBarVisibility>,
+    presenter: Gadget,
+    horizontal_bar: Gadget,
+    vertical_bar: Gadget,
+}
+
+pub async fn scrollviewer_data(viewer: &Gadget) -> ScrollViewerData {
+    let data = viewer.data.get_cloned().await;
+    let data = data.get::<ScrollViewerData>().await;
+    (*data).clone()
+}
+
+async fn scrollviewer_maximum(data: &ScrollViewerData) -> ScalarPair {
+    let extent = data.extent.get_cloned().await;
+    let viewport = data.viewport.get_cloned().await;
+    ScalarPair::new((extent.x - viewport.x).max(0.0), (extent.y - viewport.y).max(0.0))
+}
+
+fn scrollviewer_arrange_boxed(viewer: Gadget) -> Pin<Box<dyn Future<Output=()> + Send + Sync>> {
+    Box::pin(scrollviewer_arrange(viewer))
+}
+
+async fn scrollviewer_arrange(viewer: Gadget) {
+    let data = scrollviewer_data(&viewer).await;
+    let horizontal = data.horizontal.get_cloned().await;
+    let vertical = data.vertical.get_cloned().await;
+    let region = viewer.content_region().await;
+
+    // Each bar takes room from the other axis, so showing one may require the other
+    let mut bars = (horizontal == ScrollBarVisibility::Always,
+                    vertical == ScrollBarVisibility::Always);
+    let mut viewport;
+    let mut extent;
+    let mut attempts = 0;
+    loop {
+        viewport = ScalarPair::new(
+            (region.size.x - if bars.1 { SCROLLBAR_THICKNESS } else { 0.0 }).max(0.0),
+            (region.size.y - if bars.0 { SCROLLBAR_THICKNESS } else { 0.0 }).max(0.0));
+        let max = ScalarPair::new(
+            if horizontal == ScrollBarVisibility::Disabled { viewport.x } else { Scalar::INFINITY },
+            if vertical == ScrollBarVisibility::Disabled { viewport.y } else { Scalar::INFINITY });
+        let measured = data.content.measure(Constraints::loose(max)).await;
+        extent = ScalarPair::new(measured.x.max(viewport.x), measured.y.max(viewport.y));
+        let needed = (scrollviewer_bar_needed(horizontal, extent.x, viewport.x),
+                      scrollviewer_bar_needed(vertical, extent.y, viewport.y));
+        attempts += 1;
+        if needed == bars || attempts == 3 {
+            break;
+        }
+        bars = needed;
+    }
+    data.extent.set_if_changed(extent).await;
+    data.viewport.set_if_changed(viewport).await;
+    let maximum = scrollviewer_maximum(&data).await;
+    let offset = data.offset.get_cloned().await;
+    let offset = ScalarPair::new(offset.x.clamp(0.0, maximum.x), offset.y.clamp(0.0, maximum.y));
+    if data.offset.set_if_changed(offset).await {
+        // Arranged again by the offset listener
+        return;
+    }
+
+    data.presenter.pos.set_if_changed(region.origin).await;
+    data.presenter.dim.set_if_changed(viewport).await;
+    layout_place(&data.content, Region::from_origin_size(ScalarPair::zero() - offset, extent)).await;
+
+    let bar_regions = [
+        (&data.horizontal_bar, bars.0, offset.x, maximum.x, viewport.x,
+         Region::from_origin_size(region.origin + ScalarPair::new(0.0, viewport.y),
+                                  (viewport.x, SCROLLBAR_THICKNESS))),
+        (&data.vertical_bar, bars.1, offset.y, maximum.y, viewport.y,
+         Region::from_origin_size(region.origin + ScalarPair::new(viewport.x, 0.0),
+                                  (SCROLLBAR_THICKNESS, viewport.y))),
+    ];
+    for (bar, shown, value, maximum, page, bar_region) in bar_regions {
+        let bar_data = scrollbar_data(bar).await;
+        bar_data.maximum.set_if_changed(maximum).await;
+        bar_data.viewport.set_if_changed(page).await;
+        bar_data.value.set_if_changed(value).await;
+        bar.pos.set_if_changed(bar_region.origin).await;
+        bar.dim.set_if_changed(if shown { bar_region.size } else { ScalarPair::zero() }).await;
+    }
+}
+
+fn scrollviewer_bar_needed(visibility: ScrollBarVisibility, extent: Scalar, viewport: Scalar)
+    -> bool
+{
+    match visibility {
+        ScrollBarVisibility::Auto => extent > viewport,
+        ScrollBarVisibility::Always => true,
+        ScrollBarVisibility::Hidden | ScrollBarVisibility::Disabled => false,
+    }
+}
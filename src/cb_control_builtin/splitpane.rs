@@ -0,0 +1,326 @@
+use std::future::Future;
+use std::pin::Pin;
+use crate::caribou::gadget::{Gadget, GadgetRef};
+use crate::caribou::layout::{Layout, layout_arrange_on, layout_place, layout_watch_child,
+                             Orientation};
+use crate::caribou::math::{Region, Scalar, ScalarPair};
+use crate::caribou::measure::Constraints;
+use crate::caribou::state::State;
+use crate::cb_control_builtin::splitter::Splitter;
+
+/// Container showing panes side by side along its orientation, with a divider between each
+/// two of them that can be dragged, moved with the arrow keys while focused, or double-clicked
+/// to collapse the pane before it. Panes are kept within their `min_dim` and `max_dim` unless
+/// collapsed.
+pub struct SplitPane;
+
+/// Thickness of the dividers along the orientation.
+pub const SPLITPANE_DIVIDER_THICKNESS: Scalar = 6.0;
+
+impl SplitPane {
+    pub async fn create(orientation: Orientation) -> Gadget {
+        let gadget = Layout::create().await;
+
+        // Fill specialized data
+        let data = SplitPaneData {
+            orientation: State::new(gadget.refer(), orientation),
+            positions: State::new(gadget.refer(), Vec::new()),
+            collapsed: State::new(gadget.refer(), Vec::new()),
+            panes: State::new(gadget.refer(), Vec::new()),
+            dividers: State::new(gadget.refer(), Vec::new()),
+        };
+        gadget.data.set_any(data.clone()).await;
+
+        // Fill common properties
+        gadget.type_name.set("SplitPane").await;
+        gadget.set_measurer(|split, _| Box::pin(async move {
+            splitpane_measure(split).await
+        })).await;
+
+        // Listen re-layout
+        layout_arrange_on(&gadget.dim, "splitpane_arrange", splitpane_arrange_boxed, false).await;
+        layout_arrange_on(&gadget.padding, "splitpane_arrange",
+                          splitpane_arrange_boxed, true).await;
+
+        data.orientation.listen(
+            "splitpane_arrange",
+            |event| Box::pin(async move {
+                let split = event.gadget.get().unwrap();
+                let data = splitpane_data(&split).await;
+                let orientation = data.orientation.get_cloned().await;
+                for divider in data.dividers.get_cloned().await.iter() {
+                    Splitter::set_orientation(divider, orientation).await;
+                }
+                // Sizes along the old axis mean nothing along the new one
+                data.positions.set(Vec::new()).await;
+                split.invalidate_measure().await;
+                splitpane_arrange(split).await;
+            })).await;
+
+        layout_arrange_on(&data.positions, "splitpane_arrange",
+                          splitpane_arrange_boxed, false).await;
+
+        gadget
+    }
+
+    /// Appends a pane, after a new divider unless it is the first. It starts out at its
+    /// measured size.
+    pub async fn add_pane(split: &Gadget, pane: Gadget) {
+        let data = splitpane_data(split).await;
+        if !data.panes.get().await.is_empty() {
+            let orientation = data.orientation.get_cloned().await;
+            let divider = splitpane_divider_create(split, orientation).await;
+            Layout::add_child(split, divider.clone()).await;
+            data.dividers.get_mut().await.push(divider);
+        }
+        data.panes.get_mut().await.push(pane.clone());
+        Layout::add_child(split, pane.clone()).await;
+        layout_watch_child(split, &pane, splitpane_arrange_boxed).await;
+        split.invalidate_measure().await;
+        splitpane_arrange(split.clone()).await;
+    }
+
+    /// Moves a divider to a distance from the start of the container, expanding the pane before
+    /// it if it was collapsed.
+    pub async fn set_position(split: &Gadget, index: usize, position: Scalar) {
+        let data = splitpane_data(split).await;
+        let mut positions = data.positions.get_cloned().await;
+        if index >= positions.len() {
+            return;
+        }
+        positions[index] = position;
+        let mut collapsed = data.collapsed.get_cloned().await;
+        if let Some(restore) = collapsed.get_mut(index) {
+            *restore = None;
+        }
+        data.collapsed.set_if_changed(collapsed).await;
+        data.positions.set_if_changed(positions).await;
+    }
+
+    /// Collapses the pane before a divider, or expands it again to where it was.
+    pub async fn toggle_collapse(split: &Gadget, index: usize) {
+        let data = splitpane_data(split).await;
+        let mut positions = data.positions.get_cloned().await;
+        let mut collapsed = data.collapsed.get_cloned().await;
+        if index >= positions.len() || index >= collapsed.len() {
+            return;
+        }
+        match collapsed[index].take() {
+            Some(restore) => positions[index] = restore,
+            None => {
+                collapsed[index] = Some(positions[index]);
+                positions[index] = match index {
+                    0 => 0.0,
+                    _ => positions[index - 1] + SPLITPANE_DIVIDER_THICKNESS,
+                };
+            }
+        }
+        data.collapsed.set_if_changed(collapsed).await;
+        data.positions.set_if_changed(positions).await;
+    }
+}
+
+#[derive(Clone)]
+pub struct SplitPaneData {
+    pub orientation: State<Orientation>,
+    /// Distance of each divider from the start of the content, to be saved and restored.
+    pub positions: State<Vec<Scalar>>,
+    /// For each divider, where it was before the pane before it was collapsed.
+    pub collapsed: State<Vec<Option<Scalar>>>,
+    panes: State<Vec<Gadget>>,
+    dividers: State<Vec<Gadget>>,
+}
+
+pub async fn splitpane_data(split: &Gadget) -> SplitPaneData {
+    let data = split.data.get_cloned().await;
+    let data = data.get::<SplitPaneData>().await;
+    (*data).clone()
+}
+
+/// Panes at their measured size one after another, and the largest of them across.
+async fn splitpane_measure(split: Gadget) -> ScalarPair {
+    let data = splitpane_data(&split).await;
+    let orientation = data.orientation.get_cloned().await;
+    let panes = data.panes.get_cloned().await;
+    let mut main = SPLITPANE_DIVIDER_THICKNESS * panes.len().saturating_sub(1) as Scalar;
+    let mut cross: Scalar = 0.0;
+    for pane in panes.iter() {
+        let desired = pane.measure(Constraints::unbounded()).await;
+        main += orientation.main(desired);
+        cross = cross.max(orientation.cross(desired));
+    }
+    orientation.pair(main, cross) + split.padding.get_cloned().await.size()
+}
+
+fn splitpane_arrange_boxed(split: Gadget) -> Pin<Box<dyn Future<Output=()> + Send + Sync>> {
+    Box::pin(splitpane_arrange(split))
+}
+
+async fn splitpane_arrange(split: Gadget) {
+    let data = splitpane_data(&split).await;
+    let orientation = data.orientation.get_cloned().await;
+    let region = split.content_region().await;
+    let total = orientation.main(region.size);
+    let cross = orientation.cross(region.size);
+    let panes = data.panes.get_cloned().await;
+    let dividers = data.dividers.get_cloned().await;
+    if panes.is_empty() || total <= 0.0 {
+        // Positions are kept until there is room to lay out
+        return;
+    }
+
+    // New dividers go after their pane at its measured size
+    let mut positions = data.positions.get_cloned().await;
+    positions.truncate(dividers.len());
+    while positions.len() < dividers.len() {
+        let index = positions.len();
+        let start = match index {
+            0 => 0.0,
+            _ => positions[index - 1] + SPLITPANE_DIVIDER_THICKNESS,
+        };
+        let desired = panes[index]
+            .measure(Constraints::loose(orientation.pair(Scalar::INFINITY, cross))).await;
+        positions.push(start + orientation.main(desired));
+    }
+    let mut collapsed = data.collapsed.get_cloned().await;
+    collapsed.resize(dividers.len(), None);
+
+    let mut limits = Vec::with_capacity(panes.len());
+    for (index, pane) in panes.iter().enumerate() {
+        let own = pane.own_constraints().await;
+        let min = match collapsed.get(index) {
+            Some(Some(_)) => 0.0,
+            _ => orientation.main(own.min),
+        };
+        limits.push((min, orientation.main(own.max).max(min)));
+    }
+    splitpane_clamp(&mut positions, &limits, total);
+
+    data.collapsed.set_if_changed(collapsed).await;
+    if data.positions.set_if_changed(positions.clone()).await {
+        // Arranged again by the positions listener
+        return;
+    }
+
+    let mut start = 0.0;
+    for (index, pane) in panes.iter().enumerate() {
+        let end = positions.get(index).copied().unwrap_or(total);
+        layout_place(pane, Region::from_origin_size(
+            region.origin + orientation.pair(start, 0.0),
+            orientation.pair((end - start).max(0.0), cross))).await;
+        if let Some(divider) = dividers.get(index) {
+            divider.pos.set_if_changed(region.origin + orientation.pair(end, 0.0)).await;
+            divider.dim.set_if_changed(orientation.pair(SPLITPANE_DIVIDER_THICKNESS, cross)).await;
+        }
+        start = end + SPLITPANE_DIVIDER_THICKNESS;
+    }
+}
+
+/// Keeps every pane within its limits, earlier panes winning when there is not enough room.
+fn splitpane_clamp(positions: &mut [Scalar], limits: &[(Scalar, Scalar)], total: Scalar) {
+    // Backward: each pane up to the next divider, or the end for the last
+    let mut end = total;
+    for (index, position) in positions.iter_mut().enumerate().rev() {
+        let (min, max) = limits[index + 1];
+        let begin = *position + SPLITPANE_DIVIDER_THICKNESS;
+        let size = (end - begin).min(max).max(min);
+        *position = (end - size - SPLITPANE_DIVIDER_THICKNESS).max(0.0);
+        end = *position;
+    }
+    // Forward, last so that it wins: each pane from the end of the previous divider
+    let mut start = 0.0;
+    for (position, (min, max)) in positions.iter_mut().zip(limits.iter()) {
+        *position = position.min(start + max).max(start + min);
+        start = *position + SPLITPANE_DIVIDER_THICKNESS;
+    }
+}
+
+/// The split pane of a divider, with the index of the divider in it.
+async fn splitpane_divider_owner(split: &GadgetRef, divider: &Gadget)
+                                 -> Option<(Gadget, SplitPaneData, usize)> {
+    let split = split.get()?;
+    let data = splitpane_data(&split).await;
+    let index = data.dividers.get().await.iter().position(|d| d == divider)?;
+    Some((split, data, index))
+}
+
+async fn splitpane_divider_create(split: &Gadget, orientation: Orientation) -> Gadget {
+    let (position_ref, move_ref) = (split.refer(), split.refer());
+    let gadget = Splitter::create(orientation, move |divider| {
+        let split = position_ref.clone();
+        Box::pin(async move {
+            match splitpane_divider_owner(&split, &divider).await {
+                None => 0.0,
+                Some((_, data, index)) =>
+                    data.positions.get().await.get(index).copied().unwrap_or(0.0),
+            }
+        })
+    }, move |divider, position| {
+        let split = move_ref.clone();
+        Box::pin(async move {
+            if let Some((split, data, index)) = splitpane_divider_owner(&split, &divider).await {
+                splitpane_divider_move(&split, &data, index, position).await;
+            }
+        })
+    }).await;
+
+    let split_ref = split.refer();
+    Splitter::set_on_double_click(&gadget, move |divider| {
+        let split = split_ref.clone();
+        Box::pin(async move {
+            if let Some((split, _, index)) = splitpane_divider_owner(&split, &divider).await {
+                SplitPane::toggle_collapse(&split, index).await;
+            }
+        })
+    }).await;
+
+    gadget.type_name.set("SplitPaneDivider").await;
+
+    gadget
+}
+
+async fn splitpane_divider_move(split: &Gadget, data: &SplitPaneData, index: usize,
+                                position: Scalar) {
+    let total = data.orientation.get_cloned().await.main(split.content_region().await.size);
+    SplitPane::set_position(split, index, position.clamp(0.0, total)).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UNBOUNDED: (Scalar, Scalar) = (0.0, Scalar::INFINITY);
+
+    #[test]
+    fn positions_within_limits_are_kept() {
+        let mut positions = [30.0, 60.0];
+        splitpane_clamp(&mut positions, &[UNBOUNDED; 3], 100.0);
+        assert_eq!(positions, [30.0, 60.0]);
+    }
+
+    #[test]
+    fn first_pane_minimum_and_maximum() {
+        let mut positions = [10.0, 50.0];
+        splitpane_clamp(&mut positions, &[(20.0, Scalar::INFINITY), UNBOUNDED, UNBOUNDED], 100.0);
+        assert_eq!(positions, [20.0, 50.0]);
+        let mut positions = [80.0];
+        splitpane_clamp(&mut positions, &[(0.0, 50.0), UNBOUNDED], 100.0);
+        assert_eq!(positions, [50.0]);
+    }
+
+    #[test]
+    fn last_pane_minimum_pushes_divider_back() {
+        let mut positions = [90.0];
+        splitpane_clamp(&mut positions, &[UNBOUNDED, (30.0, Scalar::INFINITY)], 100.0);
+        assert_eq!(positions, [100.0 - 30.0 - SPLITPANE_DIVIDER_THICKNESS]);
+    }
+
+    #[test]
+    fn earlier_panes_win_without_enough_room() {
+        // Both panes want 60 but only 100 minus a divider is there
+        let mut positions = [60.0];
+        splitpane_clamp(&mut positions, &[(60.0, Scalar::INFINITY), (60.0, Scalar::INFINITY)],
+                        100.0);
+        assert_eq!(positions, [60.0]);
+    }
+}
@@ -0,0 +1,299 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use crate::caribou::batch::{begin_draw, begin_paint, Brush, SolidColor, Transform};
+use crate::caribou::gadget::Gadget;
+use crate::caribou::input::{CursorIcon, Key, MouseButton};
+use crate::caribou::layout::{layout_child_transform, Orientation};
+use crate::caribou::math::{Scalar, ScalarPair};
+use crate::caribou::state::State;
+
+/// Bar between two parts of a container that can be dragged along its orientation, or moved
+/// with the arrow keys while focused. The container decides what moving it means.
+pub struct Splitter;
+
+/// Where a splitter is along its orientation, measured however its container likes.
+pub type SplitterPosition = Arc<dyn Fn(Gadget)
+    -> Pin<Box<dyn Future<Output=Scalar> + Send + Sync>> + Send + Sync>;
+
+/// Moves a splitter to a position along its orientation, measured like [`SplitterPosition`],
+/// infinite to move it as far as it goes.
+pub type SplitterMove = Arc<dyn Fn(Gadget, Scalar)
+    -> Pin<Box<dyn Future<Output=()> + Send + Sync>> + Send + Sync>;
+
+/// Acts on a splitter, e.g. when it is double-clicked.
+pub type SplitterAction = Arc<dyn Fn(Gadget)
+    -> Pin<Box<dyn Future<Output=()> + Send + Sync>> + Send + Sync>;
+
+const SPLITTER_KEY_STEP: Scalar = 10.0;
+const SPLITTER_DOUBLE_CLICK: Duration = Duration::from_millis(500);
+
+impl Splitter {
+    pub async fn create<P, F>(orientation: Orientation, position: P, on_move: F) -> Gadget
+    where
+        P: Fn(Gadget) -> Pin<Box<dyn Future<Output=Scalar> + Send + Sync>>
+            + Send + Sync + 'static,
+        F: Fn(Gadget, Scalar) -> Pin<Box<dyn Future<Output=()> + Send + Sync>>
+            + Send + Sync + 'static,
+    {
+        let gadget = Gadget::default();
+
+        // Fill specialized data
+        let data = SplitterData {
+            orientation: State::new(gadget.refer(), orientation),
+            position: Arc::new(position),
+            on_move: Arc::new(on_move),
+            on_double_click: State::new(gadget.refer(), None),
+            grab: State::new(gadget.refer(), None),
+            last_press: State::new(gadget.refer(), None),
+        };
+        gadget.data.set_any(data.clone()).await;
+
+        // Fill common properties
+        gadget.type_name.set("Splitter").await;
+        gadget.accept_focus.set(true).await;
+        gadget.cursor.set(Some(splitter_cursor(orientation))).await;
+
+        data.orientation.listen(
+            "splitter_cursor",
+            |event| Box::pin(async move {
+                let orientation = event.state.get_cloned().await;
+                event.gadget.get().unwrap()
+                    .cursor.set_if_changed(Some(splitter_cursor(orientation))).await;
+            })).await;
+
+        // Listen batch update
+        gadget.dim.listen(
+            "splitter_batch_update",
+            |event| Box::pin(async move {
+                splitter_batch_update(event.gadget.get().unwrap()).await;
+            })).await;
+
+        gadget.focused.listen(
+            "splitter_batch_update",
+            |event| Box::pin(async move {
+                splitter_batch_update(event.gadget.get().unwrap()).await;
+            })).await;
+
+        gadget.mouse_pos.listen_set(
+            "splitter_batch_update",
+            |event| Box::pin(async move {
+                splitter_batch_update(event.gadget.get().unwrap()).await;
+            })).await;
+
+        gadget.mouse_pos.listen_unset(
+            "splitter_batch_update",
+            |event| Box::pin(async move {
+                let splitter = event.gadget.get().unwrap();
+                splitter_data(&splitter).await.grab.set(None).await;
+                splitter_batch_update(splitter).await;
+            })).await;
+
+        // Listen dragging
+        gadget.mouse_down.listen_add(
+            "splitter_press",
+            |event| Box::pin(async move {
+                if event.new_value != MouseButton::Primary {
+                    return;
+                }
+                let splitter = event.gadget.get().unwrap();
+                let data = splitter_data(&splitter).await;
+                if let Some(on_double_click) = data.on_double_click.get_cloned().await {
+                    let now = Instant::now();
+                    let last = data.last_press.get_cloned().await;
+                    if last.is_some_and(|last| now - last < SPLITTER_DOUBLE_CLICK) {
+                        data.last_press.set(None).await;
+                        on_double_click(splitter).await;
+                        return;
+                    }
+                    data.last_press.set(Some(now)).await;
+                }
+                if let Some(pos) = splitter.mouse_pos.get().await {
+                    let grab = SplitterGrab {
+                        position: (data.position)(splitter.clone()).await,
+                        pointer: splitter_pointer(&splitter, &data, pos).await,
+                    };
+                    data.grab.set(Some(grab)).await;
+                }
+            })).await;
+
+        gadget.mouse_down.listen_remove(
+            "splitter_press",
+            |event| Box::pin(async move {
+                if event.old_value == MouseButton::Primary {
+                    let splitter = event.gadget.get().unwrap();
+                    splitter_data(&splitter).await.grab.set(None).await;
+                }
+            })).await;
+
+        gadget.mouse_pos.listen_change(
+            "splitter_drag",
+            |event| Box::pin(async move {
+                let splitter = event.gadget.get().unwrap();
+                let data = splitter_data(&splitter).await;
+                let grab = match data.grab.get_cloned().await {
+                    None => return,
+                    Some(grab) => grab,
+                };
+                // Everything the pointer travelled since the press, which does not depend on
+                // whether the splitter caught up with the moves before
+                let pointer = splitter_pointer(&splitter, &data, event.new_value).await;
+                (data.on_move)(splitter, grab.position + pointer - grab.pointer).await;
+            })).await;
+
+        // Listen keyboard
+        gadget.key_down.listen_add(
+            "splitter_key",
+            |event| Box::pin(async move {
+                let splitter = event.gadget.get().unwrap();
+                let data = splitter_data(&splitter).await;
+                let orientation = data.orientation.get_cloned().await;
+                let step = match (orientation, event.new_value) {
+                    (Orientation::Horizontal, Key::Left) | (Orientation::Vertical, Key::Up) =>
+                        -SPLITTER_KEY_STEP,
+                    (Orientation::Horizontal, Key::Right) | (Orientation::Vertical, Key::Down) =>
+                        SPLITTER_KEY_STEP,
+                    (_, Key::Home) => Scalar::NEG_INFINITY,
+                    (_, Key::End) => Scalar::INFINITY,
+                    _ => return,
+                };
+                let position = (data.position)(splitter.clone()).await;
+                (data.on_move)(splitter, position + step).await;
+            })).await;
+
+        splitter_batch_update(gadget.clone()).await;
+
+        gadget
+    }
+
+    /// Changes the axis a splitter moves along, with its cursor.
+    pub async fn set_orientation(splitter: &Gadget, orientation: Orientation) {
+        splitter_data(splitter).await.orientation.set_if_changed(orientation).await;
+    }
+
+    pub async fn set_on_double_click<F>(splitter: &Gadget, action: F)
+    where
+        F: Fn(Gadget) -> Pin<Box<dyn Future<Output=()> + Send + Sync>> + Send + Sync + 'static,
+    {
+        splitter_data(splitter).await.on_double_click.set(Some(Arc::new(action))).await;
+    }
+}
+
+#[derive(Clone)]
+struct SplitterData {
+    orientation: State<Orientation>,
+    position: SplitterPosition,
+    on_move: SplitterMove,
+    on_double_click: State<Option<SplitterAction>>,
+    grab: State<Option<SplitterGrab>>,
+    last_press: State<Option<Instant>>,
+}
+
+/// Where a dragged splitter and the pointer were when it was pressed.
+#[derive(Clone, Copy)]
+struct SplitterGrab {
+    position: Scalar,
+    /// Along the orientation in the space of the container, which stays put while the splitter
+    /// moves.
+    pointer: Scalar,
+}
+
+async fn splitter_data(splitter: &Gadget) -> SplitterData {
+    let data = splitter.data.get_cloned().await;
+    let data = data.get::<SplitterData>().await;
+    (*data).clone()
+}
+
+async fn splitter_pointer(splitter: &Gadget, data: &SplitterData, pos: ScalarPair) -> Scalar {
+    let orientation = data.orientation.get_cloned().await;
+    orientation.main(layout_child_transform(splitter).await.map_point(pos))
+}
+
+fn splitter_cursor(orientation: Orientation) -> CursorIcon {
+    match orientation {
+        Orientation::Horizontal => CursorIcon::ResizeHorizontal,
+        Orientation::Vertical => CursorIcon::ResizeVertical,
+    }
+}
+
+async fn splitter_batch_update(splitter: Gadget) {
+    let dim = splitter.dim.get_cloned().await;
+    let hover = splitter.mouse_pos.get().await.is_some();
+    let focused = splitter.is_focused().await;
+    let fill = if hover || focused {
+        SolidColor::gray(0.75)
+    } else {
+        SolidColor::gray(0.88)
+    };
+    let batch = begin_paint()
+        .path(Transform::default(),
+              begin_draw()
+                  .rect((0.0, 0.0), dim)
+                  .finish(),
+              Brush::from_fill(fill))
+        .finish();
+    splitter.batch.set(batch).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+    use crate::caribou::{async_runtime, caribou_test_init};
+    use super::*;
+
+    /// Waits for the listeners spawned by a change to catch up.
+    async fn eventually<F: Future<Output=bool>>(condition: impl Fn() -> F) {
+        for _ in 0..200 {
+            if condition().await {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        panic!("condition not reached");
+    }
+
+    #[test]
+    fn drag_is_anchored_at_the_press() {
+        caribou_test_init();
+        async_runtime().block_on(async {
+            // A container that has not moved the splitter after any of the moves yet
+            let position = Arc::new(Mutex::new(50.0));
+            let (get, set) = (position.clone(), position.clone());
+            let splitter = Splitter::create(
+                Orientation::Horizontal,
+                move |_| {
+                    let get = get.clone();
+                    Box::pin(async move { *get.lock().unwrap() })
+                },
+                move |_, target| {
+                    let set = set.clone();
+                    Box::pin(async move { *set.lock().unwrap() = target; })
+                }).await;
+            splitter.pos.set(ScalarPair::new(50.0, 0.0)).await;
+            splitter.dim.set(ScalarPair::new(6.0, 100.0)).await;
+            let at = |expected: Scalar| {
+                let position = position.clone();
+                move || {
+                    let position = position.clone();
+                    async move { *position.lock().unwrap() == expected }
+                }
+            };
+
+            splitter.mouse_pos.put(ScalarPair::new(3.0, 10.0)).await;
+            splitter.mouse_down.push(MouseButton::Primary).await;
+            eventually(|| async { splitter_data(&splitter).await.grab.get().await.is_some() })
+                .await;
+            splitter.mouse_pos.put(ScalarPair::new(13.0, 10.0)).await;
+            eventually(at(60.0)).await;
+            splitter.mouse_pos.put(ScalarPair::new(23.0, 10.0)).await;
+            eventually(at(70.0)).await;
+            splitter.mouse_pos.put(ScalarPair::new(-7.0, 10.0)).await;
+            eventually(at(40.0)).await;
+
+            // Keys step from where the container has it
+            splitter.key_down.push(Key::Right).await;
+            eventually(at(50.0)).await;
+        });
+    }
+}
@@ -0,0 +1,593 @@
+use std::future::Future;
+use std::pin::Pin;
+use crate::caribou::batch::{begin_draw, begin_paint, Brush, SolidColor, TextAlign, Transform};
+use crate::caribou::gadget::{Gadget, GadgetParent, GadgetRef};
+use crate::caribou::input::{Key, MouseButton};
+use crate::caribou::layout::{Layout, layout_arrange_on, layout_place, layout_unwatch_child,
+                             layout_watch_child};
+use crate::caribou::math::{Insets, Region, Scalar, ScalarPair};
+use crate::caribou::measure::Constraints;
+use crate::caribou::state::State;
+use crate::cb_backend_skia_gl::text::skia_measure_text;
+
+/// Container showing one page at a time below a strip of tab headers. Tabs are switched by
+/// clicking their header, with Left/Right/Home/End while the strip is focused or with
+/// Ctrl+Tab and Ctrl+Shift+Tab from anywhere inside, and reordered by dragging their header.
+/// Headers that do not fit are scrolled with the mouse wheel. Pages not shown keep their state
+/// but neither paint nor receive input.
+pub struct TabView;
+
+/// Height of the strip of tab headers.
+pub const TAB_HEADER_HEIGHT: Scalar = 28.0;
+const TAB_CLOSE_WIDTH: Scalar = 18.0;
+const TAB_PADDING: Insets = Insets { left: 10.0, top: 0.0, right: 10.0, bottom: 0.0 };
+
+impl TabView {
+    pub async fn create() -> Gadget {
+        let gadget = Layout::create().await;
+        let strip = Layout::create().await;
+        strip.type_name.set("TabStrip").await;
+        strip.accept_focus.set(true).await;
+
+        // Fill specialized data
+        let data = TabViewData {
+            selected: State::new(gadget.refer(), None),
+            header_offset: State::new(gadget.refer(), 0.0),
+            strip: strip.clone(),
+            tabs: State::new(gadget.refer(), Vec::new()),
+            drag: State::new(gadget.refer(), None),
+        };
+        gadget.data.set_any(data.clone()).await;
+        strip.data.set_any(gadget.refer()).await;
+
+        // Fill common properties
+        gadget.type_name.set("TabView").await;
+        gadget.set_measurer(|view, constraints| Box::pin(async move {
+            let data = tabview_data(&view).await;
+            let padding = view.padding.get_cloned().await.size();
+            let header = ScalarPair::new(0.0, TAB_HEADER_HEIGHT);
+            let constraints = Constraints::loose(constraints.shrink(padding + header).max);
+            let mut desired = ScalarPair::zero();
+            for (_, page) in data.tabs.get_cloned().await.iter() {
+                let measured = page.measure(constraints).await;
+                desired = ScalarPair::new(desired.x.max(measured.x), desired.y.max(measured.y));
+            }
+            desired + header + padding
+        })).await;
+        gadget.set_key_handler(|view, event| Box::pin(async move {
+            if event.key != Key::Tab || !event.modifiers.has_control() {
+                return false;
+            }
+            TabView::cycle(&view, event.modifiers.has_shift()).await
+        })).await;
+
+        // Assemble parts
+        Layout::add_child(&gadget, strip.clone()).await;
+
+        // Listen re-layout
+        layout_arrange_on(&gadget.dim, "tabview_arrange", tabview_arrange_boxed, false).await;
+        layout_arrange_on(&gadget.padding, "tabview_arrange", tabview_arrange_boxed, true).await;
+
+        data.selected.listen(
+            "tabview_selection",
+            |event| Box::pin(async move {
+                tabview_update_selection(event.gadget.get().unwrap()).await;
+            })).await;
+
+        layout_arrange_on(&data.header_offset, "tabview_strip_arrange",
+                          tabview_strip_arrange_boxed, false).await;
+        layout_arrange_on(&data.drag, "tabview_strip_arrange",
+                          tabview_strip_arrange_boxed, false).await;
+
+        // Listen strip
+        strip.set_scroll_handler(|strip, event| Box::pin(async move {
+            let view = match tabview_strip_owner(&strip).await {
+                None => return false,
+                Some(view) => view,
+            };
+            let data = tabview_data(&view).await;
+            let offset = data.header_offset.get_cloned().await;
+            let maximum = tabview_header_maximum(&data).await;
+            let delta = event.pixels();
+            let moved = (offset + delta.x + delta.y).clamp(0.0, maximum);
+            data.header_offset.set_if_changed(moved).await
+        })).await;
+
+        strip.focused.listen(
+            "tabview_header_batch_update",
+            |event| Box::pin(async move {
+                let strip = event.gadget.get().unwrap();
+                for header in strip.children.get_vec().await.clone() {
+                    tabview_header_batch_update(header).await;
+                }
+            })).await;
+
+        strip.key_down.listen_add(
+            "tabview_strip_key",
+            |event| Box::pin(async move {
+                let view = match tabview_strip_owner(&event.gadget.get().unwrap()).await {
+                    None => return,
+                    Some(view) => view,
+                };
+                let data = tabview_data(&view).await;
+                let count = data.tabs.get().await.len();
+                if count == 0 {
+                    return;
+                }
+                let selected = data.selected.get_cloned().await.unwrap_or(0);
+                let selected = match event.new_value {
+                    Key::Left => selected.saturating_sub(1),
+                    Key::Right => (selected + 1).min(count - 1),
+                    Key::Home => 0,
+                    Key::End => count - 1,
+                    _ => return,
+                };
+                data.selected.set_if_changed(Some(selected)).await;
+            })).await;
+
+        strip.mouse_down.listen_add(
+            "tabview_strip_press",
+            |event| Box::pin(async move {
+                if event.new_value != MouseButton::Primary {
+                    return;
+                }
+                let strip = event.gadget.get().unwrap();
+                let view = match tabview_strip_owner(&strip).await {
+                    None => return,
+                    Some(view) => view,
+                };
+                let pos = match strip.mouse_pos.get().await {
+                    None => return,
+                    Some(pos) => pos,
+                };
+                let data = tabview_data(&view).await;
+                let tabs = data.tabs.get_cloned().await;
+                for (index, (header, _)) in tabs.iter().enumerate() {
+                    let origin = header.pos.get_cloned().await;
+                    let dim = header.dim.get_cloned().await;
+                    if !Region::from_origin_size(origin, dim).contains(pos) {
+                        continue;
+                    }
+                    let header_data = tabview_header_data(header).await;
+                    let local = pos - origin;
+                    if header_data.closable.get_cloned().await
+                        && tabview_close_region(dim).contains(local) {
+                        TabView::remove_tab(&view, index).await;
+                        return;
+                    }
+                    data.selected.set_if_changed(Some(index)).await;
+                    data.drag.set(Some(TabDrag { index, grab: local.x, x: pos.x })).await;
+                    return;
+                }
+            })).await;
+
+        strip.mouse_down.listen_remove(
+            "tabview_strip_press",
+            |event| Box::pin(async move {
+                if event.old_value != MouseButton::Primary {
+                    return;
+                }
+                if let Some(view) = tabview_strip_owner(&event.gadget.get().unwrap()).await {
+                    tabview_data(&view).await.drag.set_if_changed(None).await;
+                }
+            })).await;
+
+        strip.mouse_pos.listen_unset(
+            "tabview_strip_drag",
+            |event| Box::pin(async move {
+                if let Some(view) = tabview_strip_owner(&event.gadget.get().unwrap()).await {
+                    tabview_data(&view).await.drag.set_if_changed(None).await;
+                }
+            })).await;
+
+        strip.mouse_pos.listen_change(
+            "tabview_strip_drag",
+            |event| Box::pin(async move {
+                let view = match tabview_strip_owner(&event.gadget.get().unwrap()).await {
+                    None => return,
+                    Some(view) => view,
+                };
+                tabview_drag(&view, event.new_value.x).await;
+            })).await;
+
+        tabview_arrange(gadget.clone()).await;
+
+        gadget
+    }
+
+    /// Appends a tab showing a page, selected if it is the first one.
+    pub async fn add_tab(view: &Gadget, caption: &str, page: Gadget, closable: bool) {
+        let data = tabview_data(view).await;
+        let header = tabview_header_create(view, caption, closable).await;
+        Layout::add_child(&data.strip, header.clone()).await;
+        page.visible.set(false).await;
+        Layout::add_child(view, page.clone()).await;
+        layout_watch_child(view, &page, tabview_arrange_boxed).await;
+        let index = {
+            let mut tabs = data.tabs.get_mut().await;
+            tabs.push((header, page));
+            tabs.len() - 1
+        };
+        if data.selected.get_cloned().await.is_none() {
+            data.selected.set(Some(index)).await;
+        } else {
+            tabview_update_selection(view.clone()).await;
+        }
+        view.invalidate_measure().await;
+        tabview_arrange(view.clone()).await;
+    }
+
+    /// Removes a tab, returning its page. The next tab is selected if it was.
+    pub async fn remove_tab(view: &Gadget, index: usize) -> Option<Gadget> {
+        let data = tabview_data(view).await;
+        let (header, page) = {
+            let mut tabs = data.tabs.get_mut().await;
+            if index >= tabs.len() {
+                return None;
+            }
+            tabs.remove(index)
+        };
+        let count = data.tabs.get().await.len();
+        Layout::remove_child(&data.strip, header).await;
+        layout_unwatch_child(&page).await;
+        Layout::remove_child(view, page.clone()).await;
+        page.visible.set(true).await;
+        let selected = match data.selected.get_cloned().await {
+            _ if count == 0 => None,
+            Some(selected) if selected > index => Some(selected - 1),
+            Some(selected) => Some(selected.min(count - 1)),
+            None => None,
+        };
+        // The selection may keep its index while pointing at another page
+        data.selected.set(selected).await;
+        view.invalidate_measure().await;
+        tabview_arrange(view.clone()).await;
+        Some(page)
+    }
+
+    /// Moves a tab to another index, keeping it selected if it was.
+    pub async fn move_tab(view: &Gadget, from: usize, to: usize) {
+        let data = tabview_data(view).await;
+        {
+            let mut tabs = data.tabs.get_mut().await;
+            if from >= tabs.len() || to >= tabs.len() || from == to {
+                return;
+            }
+            let tab = tabs.remove(from);
+            tabs.insert(to, tab);
+        }
+        let selected = data.selected.get_cloned().await.map(|selected| match selected {
+            _ if selected == from => to,
+            _ if from < selected && selected <= to => selected - 1,
+            _ if to <= selected && selected < from => selected + 1,
+            _ => selected,
+        });
+        data.selected.set_if_changed(selected).await;
+        tabview_strip_arrange(view.clone()).await;
+    }
+
+    /// Selects the next tab, or the previous one if `backward`, wrapping around. Returns
+    /// whether there was any tab to select.
+    pub async fn cycle(view: &Gadget, backward: bool) -> bool {
+        let data = tabview_data(view).await;
+        let count = data.tabs.get().await.len();
+        if count == 0 {
+            return false;
+        }
+        let selected = match (data.selected.get_cloned().await, backward) {
+            (None, _) => 0,
+            (Some(selected), false) => (selected + 1) % count,
+            (Some(selected), true) => (selected + count - 1) % count,
+        };
+        data.selected.set_if_changed(Some(selected)).await;
+        true
+    }
+}
+
+#[derive(Clone)]
+pub struct TabViewData {
+    pub selected: State<Option<usize>>,
+    /// How far the headers are scrolled when they do not fit the strip.
+    pub header_offset: State<Scalar>,
+    strip: Gadget,
+    /// Header and page of each tab, in header order.
+    tabs: State<Vec<(Gadget, Gadget)>>,
+    drag: State<Option<TabDrag>>,
+}
+
+pub async fn tabview_data(view: &Gadget) -> TabViewData {
+    let data = view.data.get_cloned().await;
+    let data = data.get::<TabViewData>().await;
+    (*data).clone()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct TabDrag {
+    /// Index of the dragged tab, following it as it is reordered.
+    index: usize,
+    /// Pointer position within the header where it was grabbed.
+    grab: Scalar,
+    /// Pointer position along the strip.
+    x: Scalar,
+}
+
+async fn tabview_strip_owner(strip: &Gadget) -> Option<Gadget> {
+    let data = strip.data.get_cloned().await;
+    let view = data.get::<GadgetRef>().await;
+    view.get()
+}
+
+fn tabview_arrange_boxed(view: Gadget) -> Pin<Box<dyn Future<Output=()> + Send + Sync>> {
+    Box::pin(tabview_arrange(view))
+}
+
+async fn tabview_arrange(view: Gadget) {
+    let data = tabview_data(&view).await;
+    let region = view.content_region().await;
+    let header = TAB_HEADER_HEIGHT.min(region.size.y);
+    data.strip.pos.set_if_changed(region.origin).await;
+    data.strip.dim.set_if_changed(ScalarPair::new(region.size.x, header)).await;
+    let page_region = Region::from_origin_size(
+        region.origin + ScalarPair::new(0.0, header),
+        (region.size.x, region.size.y - header));
+    // Hidden pages are laid out too, so they are ready when shown
+    for (_, page) in data.tabs.get_cloned().await.iter() {
+        layout_place(page, page_region).await;
+    }
+    tabview_strip_arrange(view).await;
+}
+
+async fn tabview_header_widths(data: &TabViewData) -> Vec<Scalar> {
+    let mut widths = Vec::new();
+    for (header, _) in data.tabs.get_cloned().await.iter() {
+        widths.push(header.measure(Constraints::unbounded()).await.x);
+    }
+    widths
+}
+
+async fn tabview_header_maximum(data: &TabViewData) -> Scalar {
+    let total: Scalar = tabview_header_widths(data).await.iter().sum();
+    (total - data.strip.dim.get_cloned().await.x).max(0.0)
+}
+
+fn tabview_strip_arrange_boxed(view: Gadget) -> Pin<Box<dyn Future<Output=()> + Send + Sync>> {
+    Box::pin(tabview_strip_arrange(view))
+}
+
+async fn tabview_strip_arrange(view: Gadget) {
+    let data = tabview_data(&view).await;
+    let widths = tabview_header_widths(&data).await;
+    let maximum = tabview_header_maximum(&data).await;
+    let offset = data.header_offset.get_cloned().await;
+    if data.header_offset.set_if_changed(offset.clamp(0.0, maximum)).await {
+        // Arranged again by the offset listener
+        return;
+    }
+    let offset = offset.clamp(0.0, maximum);
+    let drag = data.drag.get_cloned().await;
+    let mut start = -offset;
+    for (index, ((header, _), width)) in data.tabs.get_cloned().await.iter()
+        .zip(widths.iter()).enumerate() {
+        let x = match drag {
+            // The dragged header follows the pointer, so it stays where it was grabbed
+            Some(drag) if drag.index == index => drag.x - drag.grab,
+            _ => start,
+        };
+        header.pos.set_if_changed(ScalarPair::new(x, 0.0)).await;
+        header.dim.set_if_changed(ScalarPair::new(*width, TAB_HEADER_HEIGHT)).await;
+        start += width;
+    }
+}
+
+/// Moves the dragged header to the pointer, reordering the tab once its center passes that of
+/// a neighbour.
+async fn tabview_drag(view: &Gadget, x: Scalar) {
+    let data = tabview_data(view).await;
+    let drag = match data.drag.get_cloned().await {
+        None => return,
+        Some(drag) => drag,
+    };
+    let widths = tabview_header_widths(&data).await;
+    let width = match widths.get(drag.index) {
+        None => return,
+        Some(width) => *width,
+    };
+    let center = x - drag.grab + width / 2.0 + data.header_offset.get_cloned().await;
+    let mut start = 0.0;
+    let mut target = 0;
+    for (index, other) in widths.iter().enumerate() {
+        if index != drag.index && start + other / 2.0 < center {
+            target += 1;
+        }
+        start += other;
+    }
+    if target != drag.index {
+        TabView::move_tab(view, drag.index, target).await;
+    }
+    data.drag.set(Some(TabDrag { index: target, x, ..drag })).await;
+}
+
+/// Shows the selected page only, marks its header and scrolls it into view. The focus leaves a
+/// page being hidden for the strip.
+async fn tabview_update_selection(view: Gadget) {
+    let data = tabview_data(&view).await;
+    let selected = data.selected.get_cloned().await;
+    let tabs = data.tabs.get_cloned().await;
+    let focused = match view.get_window().await.and_then(|wr| wr.get()) {
+        None => None,
+        Some(window) => window.cb_focus.focused.get().await
+            .and_then(|gr| gr.get())
+            .map(|focused| (window, focused)),
+    };
+    for (index, (header, page)) in tabs.iter().enumerate() {
+        let shown = selected == Some(index);
+        tabview_header_data(header).await.selected.set_if_changed(shown).await;
+        if page.visible.set_if_changed(shown).await && !shown {
+            if let Some((window, focused)) = &focused {
+                if tabview_is_within(focused, page).await {
+                    window.cb_focus.focus(&data.strip).await;
+                }
+            }
+        }
+    }
+
+    // Scroll the selected header into view
+    if let Some(selected) = selected {
+        let widths = tabview_header_widths(&data).await;
+        if let Some(width) = widths.get(selected) {
+            let start: Scalar = widths[..selected].iter().sum();
+            let visible = data.strip.dim.get_cloned().await.x;
+            let offset = data.header_offset.get_cloned().await;
+            let offset = offset.min(start).max(start + width - visible);
+            data.header_offset.set_if_changed(offset.max(0.0)).await;
+        }
+    }
+}
+
+async fn tabview_is_within(gadget: &Gadget, ancestor: &Gadget) -> bool {
+    let mut cur = gadget.clone();
+    loop {
+        if &cur == ancestor {
+            return true;
+        }
+        cur = match cur.parent.get_cloned().await {
+            GadgetParent::Gadget(gr) => match gr.get() {
+                None => return false,
+                Some(parent) => parent,
+            },
+            _ => return false,
+        };
+    }
+}
+
+#[derive(Clone)]
+struct TabHeaderData {
+    caption: State<String>,
+    closable: State<bool>,
+    selected: State<bool>,
+}
+
+async fn tabview_header_data(header: &Gadget) -> TabHeaderData {
+    let data = header.data.get_cloned().await;
+    let data = data.get::<TabHeaderData>().await;
+    (*data).clone()
+}
+
+fn tabview_close_region(dim: ScalarPair) -> Region {
+    Region::from_origin_size(
+        ScalarPair::new(dim.x - TAB_PADDING.right - TAB_CLOSE_WIDTH, 0.0),
+        (TAB_CLOSE_WIDTH, dim.y))
+}
+
+async fn tabview_header_create(view: &Gadget, caption: &str, closable: bool) -> Gadget {
+    let gadget = Gadget::default();
+
+    // Fill specialized data
+    let data = TabHeaderData {
+        caption: State::new(gadget.refer(), caption.to_string()),
+        closable: State::new(gadget.refer(), closable),
+        selected: State::new(gadget.refer(), false),
+    };
+    gadget.data.set_any(data.clone()).await;
+
+    // Fill common properties
+    gadget.type_name.set("TabHeader").await;
+    gadget.font.set(view.font.get_cloned().await).await;
+    gadget.padding.set(TAB_PADDING).await;
+    gadget.set_measurer(|header, _| Box::pin(async move {
+        let data = tabview_header_data(&header).await;
+        let font = header.font.get_cloned().await;
+        let caption = skia_measure_text(&font, &data.caption.get_cloned().await);
+        let close = if data.closable.get_cloned().await { TAB_CLOSE_WIDTH } else { 0.0 };
+        let padding = header.padding.get_cloned().await.size();
+        ScalarPair::new(caption.x + close + padding.x, TAB_HEADER_HEIGHT)
+    })).await;
+
+    // Listen batch update
+    gadget.dim.listen(
+        "tabview_header_batch_update",
+        |event| Box::pin(async move {
+            tabview_header_batch_update(event.gadget.get().unwrap()).await;
+        })).await;
+
+    gadget.mouse_pos.listen_set(
+        "tabview_header_batch_update",
+        |event| Box::pin(async move {
+            tabview_header_batch_update(event.gadget.get().unwrap()).await;
+        })).await;
+
+    gadget.mouse_pos.listen_unset(
+        "tabview_header_batch_update",
+        |event| Box::pin(async move {
+            tabview_header_batch_update(event.gadget.get().unwrap()).await;
+        })).await;
+
+    data.selected.listen(
+        "tabview_header_batch_update",
+        |event| Box::pin(async move {
+            tabview_header_batch_update(event.gadget.get().unwrap()).await;
+        })).await;
+
+    data.caption.listen(
+        "tabview_header_batch_update",
+        |event| Box::pin(async move {
+            let header = event.gadget.get().unwrap();
+            header.invalidate_measure().await;
+            tabview_header_batch_update(header).await;
+        })).await;
+
+    tabview_header_batch_update(gadget.clone()).await;
+
+    gadget
+}
+
+async fn tabview_header_batch_update(header: Gadget) {
+    let data = tabview_header_data(&header).await;
+    let dim = header.dim.get_cloned().await;
+    let content = header.content_region().await;
+    let font = header.font.get_cloned().await;
+    let selected = data.selected.get_cloned().await;
+    let closable = data.closable.get_cloned().await;
+    let hover = header.mouse_pos.get().await.is_some();
+    let strip_focused = match header.parent.get_cloned().await {
+        GadgetParent::Gadget(gr) => match gr.get() {
+            None => false,
+            Some(strip) => strip.is_focused().await,
+        },
+        _ => false,
+    };
+    let fill = match (selected, hover) {
+        (true, _) => SolidColor::gray(1.0),
+        (false, true) => SolidColor::gray(0.9),
+        (false, false) => SolidColor::gray(0.82),
+    };
+    let caption_region = Region::from_origin_size(
+        content.origin,
+        (content.size.x - if closable { TAB_CLOSE_WIDTH } else { 0.0 }, content.size.y));
+    let mut painting = begin_paint()
+        .path(Transform::default(),
+              begin_draw()
+                  .rect((0.0, 0.0), dim - ScalarPair::new(1.0, 0.0))
+                  .finish(),
+              Brush::from_fill(fill))
+        .text(Transform::from_translate(caption_region.center()),
+              data.caption.get_cloned().await, font.clone(),
+              TextAlign::Center,
+              Brush::from_fill(SolidColor::gray(0.1)));
+    if closable {
+        painting = painting
+            .text(Transform::from_translate(tabview_close_region(dim).center()),
+                  String::from("×"), font,
+                  TextAlign::Center,
+                  Brush::from_fill(SolidColor::gray(0.4)));
+    }
+    if selected && strip_focused {
+        painting = painting
+            .path(Transform::default(),
+                  begin_draw()
+                      .rect((0.0, 0.0), (dim.x - 1.0, 2.0))
+                      .finish(),
+                  Brush::from_fill(SolidColor::gray(0.3)));
+    }
+    header.batch.set(painting.finish()).await;
+}
@@ -1,18 +1,24 @@
 use crate::caribou::batch::{begin_draw, begin_paint, Brush, Colors, Material, Painting, SolidColor, Transform};
 use crate::caribou::gadget::Gadget;
-use crate::caribou::math::ScalarPair;
+use crate::caribou::input::CursorIcon;
+use crate::caribou::math::{Insets, ScalarPair};
 use crate::caribou::state::{Arbitrary, State};
 
 pub struct Textbox;
 
+const TEXTBOX_PADDING: Insets = Insets { left: 6.0, top: 4.0, right: 6.0, bottom: 4.0 };
+
 impl Textbox {
     pub async fn create(style: TextboxStyle) -> Gadget {
         let gadget = Gadget::default();
 
         // Fill common properties
+        gadget.type_name.set("Textbox").await;
         gadget.dim.set(ScalarPair::new(100.0, 30.0)).await;
         gadget.accept_focus.set(true).await;
         gadget.lock_focus.set(false).await;
+        gadget.cursor.set(Some(CursorIcon::Text)).await;
+        gadget.padding.set(TEXTBOX_PADDING).await;
 
         // Listen property updates
         gadget.dim.listen(
@@ -16,7 +16,6 @@ fn main() {
     caribou_init();
     let window = async_runtime().block_on(async {
         let layout = Layout::create().await;
-        layout.dim.set((800.0, 600.0).into()).await;
 
         let button1 = Button::create(ButtonStyle::default()).await;
         //button1.enabled.set(false).await;